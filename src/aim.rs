@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use crate::blocktype::BlockRegistry;
+use crate::constants::CONFIG;
+use crate::launch_direction;
+use crate::entity::{AimDot, AimTarget, Block};
+use crate::gameassets::GameAssets;
+use crate::resource::{LaunchPoint, Playfield};
+
+/// Distance between two dots.
+pub const DOT_SPACING: f32 = 25.;
+/// Distance the path is advanced per collision check.
+const MARCH_STEP: f32 = 5.;
+
+/// Longest path the preview follows: up to the top wall and back down to the
+/// floor at the flattest launch angle.
+pub fn max_path_length(playfield: &Playfield) -> f32 {
+    // flatter paths are cut short rather than followed forever
+    let min_rise = CONFIG.min_launch_angle.to_radians().sin().max(0.05);
+    2. * playfield.size().y / min_rise
+}
+
+/// Dots needed to draw the longest path.
+pub fn aim_dots(playfield: &Playfield) -> usize {
+    (max_path_length(playfield) / DOT_SPACING).ceil() as usize
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AimPrediction {
+    /// Dot positions from the launch point on.
+    pub points: Vec<Vec2>,
+    /// Center of the first solid block on the path.
+    pub hit: Option<Vec2>,
+}
+
+/// Follows a ball launched from `start` towards `direction`, bouncing off the
+/// side and top walls around `playfield`. The path ends at the first of
+/// `blocks` it touches or back on the floor.
+pub fn predict(playfield: &Playfield, start: Vec2, direction: Vec2, blocks: &[Vec2]) -> AimPrediction {
+    let mut prediction = AimPrediction::default();
+    if direction.length_squared() == 0. {
+        return prediction;
+    }
+    let mut direction = direction.normalize();
+    let mut position = start;
+    // the ball's collider has a radius of `ball_size`
+    let reach = playfield.cell_size / 2. + CONFIG.ball_size;
+    // the ball's center turns around one radius before the wall
+    let min = playfield.min() + Vec2::splat(CONFIG.ball_size);
+    let max = playfield.max() - Vec2::splat(CONFIG.ball_size);
+    let max_length = max_path_length(playfield);
+    let mut length = 0.;
+    let mut travelled = 0.;
+    while length < max_length {
+        position += direction * MARCH_STEP;
+        length += MARCH_STEP;
+        travelled += MARCH_STEP;
+        if position.y < start.y {
+            break;
+        }
+        if position.y > max.y && direction.y > 0. {
+            direction.y = -direction.y;
+        }
+        if (position.x < min.x && direction.x < 0.) || (position.x > max.x && direction.x > 0.) {
+            direction.x = -direction.x;
+        }
+        if let Some(block) = blocks
+            .iter()
+            .find(|block| (position.x - block.x).abs() < reach && (position.y - block.y).abs() < reach)
+        {
+            prediction.hit = Some(*block);
+            break;
+        }
+        if travelled >= DOT_SPACING {
+            travelled = 0.;
+            prediction.points.push(position);
+        }
+    }
+    prediction
+}
+
+pub fn aim_preview_setup_system(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    playfield: Res<Playfield>,
+) {
+    let dot = assets.aim_dot.clone();
+    for _ in 0..aim_dots(&playfield) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: dot.clone(),
+                sprite: Sprite::new(Vec2::new(CONFIG.ball_size / 3., CONFIG.ball_size / 3.)),
+                visible: Visible { is_visible: false, is_transparent: false },
+                ..Default::default()
+            })
+            .insert(AimDot);
+    }
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: assets.aim_target.clone(),
+            sprite: Sprite::new(Vec2::new(playfield.cell_size, playfield.cell_size)),
+            visible: Visible { is_visible: false, is_transparent: true },
+            ..Default::default()
+        })
+        .insert(AimTarget);
+}
+
+pub fn aim_preview_system(
+    windows: Res<Windows>,
+    launch_point: Res<LaunchPoint>,
+    playfield: Res<Playfield>,
+    registry: Res<BlockRegistry>,
+    block_query: Query<(&Transform, &Block)>,
+    mut dot_query: Query<(&mut Transform, &mut Visible), (With<AimDot>, Without<Block>, Without<AimTarget>)>,
+    mut target_query: Query<(&mut Transform, &mut Visible), (With<AimTarget>, Without<Block>, Without<AimDot>)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let cursor = match window.cursor_position() {
+        Some(cursor) => playfield.window_to_world(cursor, Vec2::new(window.width(), window.height())),
+        None => return,
+    };
+    // sensors let the ball pass
+    let blocks: Vec<Vec2> = block_query
+        .iter()
+        .filter(|(_, block)| registry.is_solid(&block.kind))
+        .map(|(transform, _)| transform.translation.truncate())
+        .collect();
+    let start = launch_point.position(&playfield);
+    let prediction = predict(&playfield, start, launch_direction(cursor, start), &blocks);
+
+    let mut points = prediction.points.iter();
+    for (mut transform, mut visible) in dot_query.iter_mut() {
+        match points.next() {
+            Some(point) => {
+                transform.translation = point.extend(2.);
+                visible.is_visible = true;
+            },
+            None => visible.is_visible = false,
+        }
+    }
+    for (mut transform, mut visible) in target_query.iter_mut() {
+        match prediction.hit {
+            Some(hit) => {
+                transform.translation = hit.extend(0.5);
+                visible.is_visible = true;
+            },
+            None => visible.is_visible = false,
+        }
+    }
+}
+
+pub fn despawn_aim_preview_system(
+    mut commands: Commands,
+    preview_query: Query<Entity, Or<(With<AimDot>, With<AimTarget>)>>,
+) {
+    preview_query
+    .iter()
+    .for_each(|e| commands.entity(e).despawn_recursive());
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+use heron::CollisionShape;
+use ron::de::from_reader;
+use serde::Deserialize;
+
+pub const BLOCKSPATH: &str = "assets/config/blocks.ron";
+
+/// Kinds the map generator places.
+pub const STANDARD: &str = "standard";
+pub const ADD_BALL: &str = "add_ball";
+pub const BOMB: &str = "bomb";
+pub const LASER_ROW: &str = "laser_row";
+pub const LASER_COLUMN: &str = "laser_column";
+pub const SPLITTER: &str = "splitter";
+pub const DEFLECTOR: &str = "deflector";
+pub const WALL: &str = "wall";
+pub const ARMORED: &str = "armored";
+pub const CIRCLE: &str = "circle";
+/// Triangles by the corner of their right angle, in `Corner::ALL` order.
+pub const TRIANGLES: [&str; 4] = ["triangle_bottom_left", "triangle_bottom_right", "triangle_top_left", "triangle_top_right"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Axis {
+    Row,
+    Column,
+}
+
+/// What a block does when a ball touches it.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum HitEffect {
+    /// Loses one health and awards `Scoreboard::HIT_POINTS`.
+    Damage,
+    /// Like `Damage`, but only for balls coming from above.
+    DamageFromAbove,
+    /// The shooter gets one more ball.
+    AddBall,
+    /// Destroyed by the first touch.
+    Consume,
+    /// Deals one damage to every damageable block in the same row or column.
+    Laser(Axis),
+    /// Spawns an extra ball at the hit point with a mirrored velocity.
+    Split,
+    /// Sends the ball off in a random direction.
+    Deflect,
+}
+
+/// What happens when a block's health reaches zero.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum DestroyEffect {
+    /// Awards `Scoreboard::DESTROY_POINTS`.
+    Score,
+    /// Deals `damage` to every damageable block up to `radius` cells away,
+    /// 1 covers the surrounding 3x3 square.
+    Explode { radius: i32, damage: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum BlockShape {
+    /// Fills the whole cell.
+    Square,
+    /// Touches the four cell edges.
+    Circle,
+    /// Half of the cell, cut diagonally, with the right angle in the given
+    /// corner.
+    Triangle(Corner),
+}
+impl Default for BlockShape {
+    fn default() -> Self {
+        BlockShape::Square
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Corner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+impl Corner {
+    pub const ALL: [Corner; 4] = [Corner::BottomLeft, Corner::BottomRight, Corner::TopLeft, Corner::TopRight];
+
+    /// Points from the cell center towards the corner.
+    pub fn direction(&self) -> Vec2 {
+        match self {
+            Corner::BottomLeft => Vec2::new(-1., -1.),
+            Corner::BottomRight => Vec2::new(1., -1.),
+            Corner::TopLeft => Vec2::new(-1., 1.),
+            Corner::TopRight => Vec2::new(1., 1.),
+        }
+    }
+}
+
+const CIRCLE_TEXTURE: HandleUntyped = HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5b1c_90e2_7d43_a001);
+const TRIANGLE_TEXTURES: [HandleUntyped; 4] = [
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5b1c_90e2_7d43_a002),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5b1c_90e2_7d43_a003),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5b1c_90e2_7d43_a004),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5b1c_90e2_7d43_a005),
+];
+/// Side length of the generated shape masks in pixels.
+const MASK_SIZE: u32 = 64;
+
+impl BlockShape {
+    /// White mask for shapes a plain sprite can't draw, tinted by the
+    /// block's color.
+    pub fn texture(&self) -> Option<Handle<Texture>> {
+        match self {
+            BlockShape::Square => None,
+            BlockShape::Circle => Some(CIRCLE_TEXTURE.typed()),
+            BlockShape::Triangle(corner) => {
+                let index = Corner::ALL.iter().position(|c| c == corner).unwrap();
+                Some(TRIANGLE_TEXTURES[index].typed())
+            },
+        }
+    }
+
+    /// Collider matching the drawn shape in a cell of `size`.
+    pub fn collider(&self, size: f32, sensor: bool) -> CollisionShape {
+        let half = size / 2.;
+        match self {
+            BlockShape::Square => CollisionShape::Cuboid {
+                half_extends: Vec3::new(half, half, 0.),
+                border_radius: if sensor { None } else { Some(1.) },
+            },
+            BlockShape::Circle => CollisionShape::Sphere { radius: half },
+            BlockShape::Triangle(corner) => {
+                let c = corner.direction() * half;
+                CollisionShape::ConvexHull {
+                    points: vec![
+                        Vec3::new(c.x, c.y, 0.),
+                        Vec3::new(-c.x, c.y, 0.),
+                        Vec3::new(c.x, -c.y, 0.),
+                    ],
+                }
+            },
+        }
+    }
+
+    /// Where the health text sits, relative to the cell center.
+    pub fn label_offset(&self, size: f32) -> Vec2 {
+        match self {
+            // the centroid
+            BlockShape::Triangle(corner) => corner.direction() * size / 6.,
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// Whether the point `uv`, from -1 to 1 over the cell, is inside.
+    fn contains(&self, uv: Vec2) -> bool {
+        match self {
+            BlockShape::Square => true,
+            BlockShape::Circle => uv.length_squared() <= 1.,
+            BlockShape::Triangle(corner) => uv.dot(corner.direction()) >= 0.,
+        }
+    }
+
+    fn mask(&self) -> Texture {
+        let mut data = Vec::with_capacity((MASK_SIZE * MASK_SIZE * 4) as usize);
+        // rows run top to bottom
+        for row in 0..MASK_SIZE {
+            for column in 0..MASK_SIZE {
+                let uv = Vec2::new(column as f32 + 0.5, (MASK_SIZE - row) as f32 - 0.5) / MASK_SIZE as f32 * 2. - Vec2::ONE;
+                let alpha = if self.contains(uv) { 255 } else { 0 };
+                data.extend_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+        Texture::new(
+            Extent3d::new(MASK_SIZE, MASK_SIZE, 1),
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
+
+/// Generates the masks behind `BlockShape::texture`.
+pub fn shape_texture_setup_system(mut textures: ResMut<Assets<Texture>>) {
+    textures.set_untracked(CIRCLE_TEXTURE, BlockShape::Circle.mask());
+    for (handle, corner) in TRIANGLE_TEXTURES.iter().zip(Corner::ALL.iter()) {
+        textures.set_untracked(handle.clone(), BlockShape::Triangle(*corner).mask());
+    }
+}
+
+/// One entry of `blocks.ron`, everything needed to spawn and play a block
+/// type.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockDef {
+    /// Texture below `assets/`, plain colored blocks if unset.
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// Block color, tints the sprite if there is one.
+    #[serde(default = "white")]
+    pub color: (f32, f32, f32),
+    /// Shades blocks from this color at one health up to `color` for the
+    /// strongest block on the field, plain `color` if unset.
+    #[serde(default)]
+    pub weak_color: Option<(f32, f32, f32)>,
+    #[serde(default)]
+    pub shape: BlockShape,
+    /// Sensors let balls pass instead of bouncing them off.
+    #[serde(default)]
+    pub sensor: bool,
+    /// Fixed text shown on the block, the health is shown if unset.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Never destroyed by balls. Permanent blocks don't need to be cleared to
+    /// win and drop off the board at the loss line instead of ending the game.
+    #[serde(default)]
+    pub permanent: bool,
+    #[serde(default)]
+    pub on_hit: Vec<HitEffect>,
+    #[serde(default)]
+    pub on_destroy: Vec<DestroyEffect>,
+}
+
+fn white() -> (f32, f32, f32) {
+    (1., 1., 1.)
+}
+
+impl BlockDef {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.color.0, self.color.1, self.color.2)
+    }
+}
+
+/// Shared materials of the blocks shaded by health, blocks of one kind and
+/// shade use the same material.
+#[derive(Debug, Default)]
+pub struct BlockPalette {
+    /// Highest health among the shaded blocks on the field.
+    pub max_health: u32,
+    shades: HashMap<(String, usize), Handle<ColorMaterial>>,
+    flashes: HashMap<Option<Handle<Texture>>, Handle<ColorMaterial>>,
+}
+impl BlockPalette {
+    pub const SHADES: usize = 8;
+
+    /// Shade of a block with `health`, from 0 for the weakest to
+    /// `SHADES - 1` for the strongest.
+    pub fn shade(&self, health: u32) -> usize {
+        if self.max_health <= 1 {
+            return Self::SHADES - 1;
+        }
+        let relative = health.min(self.max_health).saturating_sub(1) as f32 / (self.max_health - 1) as f32;
+        (relative * (Self::SHADES - 1) as f32).round() as usize
+    }
+
+    pub fn material(
+        &mut self,
+        kind: &str,
+        def: &BlockDef,
+        health: u32,
+        texture: Option<Handle<Texture>>,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        let shade = self.shade(health);
+        self.shades
+            .entry((kind.to_string(), shade))
+            .or_insert_with(|| {
+                let weak = def.weak_color.unwrap_or(def.color);
+                let t = shade as f32 / (Self::SHADES - 1) as f32;
+                let lerp = |from: f32, to: f32| from + (to - from) * t;
+                let color = Color::rgb(lerp(weak.0, def.color.0), lerp(weak.1, def.color.1), lerp(weak.2, def.color.2));
+                materials.add(ColorMaterial { color, texture })
+            })
+            .clone()
+    }
+
+    /// Translucent white drawn over a block that was hit.
+    pub fn flash(&mut self, texture: Option<Handle<Texture>>, materials: &mut Assets<ColorMaterial>) -> Handle<ColorMaterial> {
+        self.flashes
+            .entry(texture.clone())
+            .or_insert_with(|| materials.add(ColorMaterial { color: Color::rgba(1., 1., 1., 0.6), texture }))
+            .clone()
+    }
+}
+
+/// All block types by name, new bricks are added in `blocks.ron`.
+#[derive(Debug, Clone)]
+pub struct BlockRegistry {
+    pub types: HashMap<String, BlockDef>,
+}
+impl BlockRegistry {
+    pub fn load(path: &str) -> Self {
+        BlockRegistry {
+            types: from_reader(File::open(path).expect("Failed opening file")).unwrap(),
+        }
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&BlockDef> {
+        let def = self.types.get(kind);
+        if def.is_none() {
+            warn!("unknown block type {}", kind);
+        }
+        def
+    }
+
+    /// Whether blocks of this kind lose health when hit.
+    pub fn is_damageable(&self, kind: &str) -> bool {
+        self.get(kind).map_or(false, |def| {
+            def.on_hit.iter().any(|effect| matches!(effect, HitEffect::Damage | HitEffect::DamageFromAbove))
+        })
+    }
+
+    pub fn is_permanent(&self, kind: &str) -> bool {
+        self.get(kind).map_or(false, |def| def.permanent)
+    }
+
+    /// Whether blocks of this kind have to be cleared to win.
+    pub fn is_required(&self, kind: &str) -> bool {
+        self.get(kind).map_or(false, |def| !def.permanent)
+    }
+
+    /// Whether balls bounce off blocks of this kind.
+    pub fn is_solid(&self, kind: &str) -> bool {
+        self.get(kind).map_or(false, |def| !def.sensor)
+    }
+}
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        BlockRegistry::load(BLOCKSPATH)
+    }
+}
+
+/// Whether a ball at `ball` touched the block at `block` on its top side.
+pub fn hit_from_above(ball: Vec2, block: Vec2) -> bool {
+    let offset = ball - block;
+    offset.y > offset.x.abs()
+}
@@ -1,92 +1,94 @@
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::render::render_graph::base::MainPass;
 use bevy::text::Text2dSize;
 use heron::{CollisionLayers, CollisionShape, PhysicMaterial, RigidBody, Velocity};
-use crate::direction_ball_to_mouse;
 use crate::components::{CollisionLayer};
 use crate::constants::CONFIG;
-use crate::entity::{Ball, Block};
-use crate::resource::MousePos;
+use crate::entity::{Ball, BallAge, Block, Floor, GridPos};
+use crate::blocktype::BlockRegistry;
+use crate::gameassets::GameAssets;
+use crate::level::Brick;
+use crate::resource::Playfield;
 
-type FieldPos = (usize, usize);
+pub type FieldPos = (usize, usize);
 
-pub fn construct_block_standard(
-    commands: &mut Commands,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    asset_server: &Res<AssetServer>,
-    field_pos: FieldPos,
-    health: u32,
-) {
-    let xy = field_pos_to_transform(field_pos);
-    commands
-        .spawn_bundle(SpriteBundle {
-            material: materials.add(Color::rgb(0.2, 0.8, 0.6).into()),
-            transform: Transform::from_xyz(xy.0, xy.1, 0.),
-            sprite: Sprite::new(Vec2::new(CONFIG.block_size, CONFIG.block_size)),
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            parent.spawn_bundle(Text2dBundle {
-                draw: Draw {
-                    ..Default::default()
-                },
-                visible: Visible {
-                    is_transparent: true,
-                    ..Default::default()
-                },
-                text: Text::with_section(
-                    // Accepts a `String` or any type that converts into a `String`, such as `&str`
-                    health.to_string(),
-                    TextStyle {
-                        font: asset_server.load("fonts/Uroob-Regular.ttf"),
-                        font_size: CONFIG.block_size / 3. + 10.,
-                        color: Color::BLACK,
-                    },
-                    Default::default(),
-                ),
-                transform: Transform::from_xyz(CONFIG.block_size/10., -CONFIG.block_size / 3., 0.1),
-                global_transform: Default::default(),
-                main_pass: MainPass {},
-                text_2d_size: Text2dSize {
-                    size: Size::default(),
-                },
-            });
-        })
-        .insert(RigidBody::Static)
-        .insert(CollisionShape::Cuboid {
-            half_extends: Vec3::new(CONFIG.block_size / 2., CONFIG.block_size / 2.,0.),
-            border_radius: Some(1.),
-        })  
-        .insert(PhysicMaterial {
-            restitution: 1.,
-            ..Default::default()
-        })
-        .insert(
-            CollisionLayers::none()
-                .with_group(CollisionLayer::BlockStandard)
-                .with_mask(CollisionLayer::Ball),
-        )
-        .insert(Block::Standard(health));
-        
+/// Everything `construct_block` needs, for systems spawning blocks.
+#[derive(SystemParam)]
+pub struct BlockSpawner<'a> {
+    pub commands: Commands<'a>,
+    pub assets: ResMut<'a, GameAssets>,
+    pub materials: ResMut<'a, Assets<ColorMaterial>>,
+    pub registry: Res<'a, BlockRegistry>,
+    pub playfield: Res<'a, Playfield>,
+}
+
+impl<'a> BlockSpawner<'a> {
+    pub fn block(&mut self, field_pos: (i32, i32), block: Block) -> Option<Entity> {
+        construct_block(&mut self.commands, &mut self.assets, &mut self.materials, &self.registry, &self.playfield, field_pos, block)
+    }
+
+    pub fn brick(&mut self, brick: &Brick) -> Option<Entity> {
+        construct_brick(&mut self.commands, &mut self.assets, &mut self.materials, &self.registry, &self.playfield, brick)
+    }
 }
 
-pub fn construct_block_add_ball(
+/// Everything `construct_ball` needs, for systems spawning balls.
+#[derive(SystemParam)]
+pub struct BallSpawner<'a> {
+    pub commands: Commands<'a>,
+    pub assets: Res<'a, GameAssets>,
+}
+impl<'a> BallSpawner<'a> {
+    pub fn ball(&mut self, position: Vec2, velocity: Vec2) -> Entity {
+        construct_ball(&mut self.commands, &self.assets, position, velocity)
+    }
+}
+
+/// Spawns the block for a generated or hand-authored brick.
+pub fn construct_brick(
     commands: &mut Commands,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    asset_server: &Res<AssetServer>,
-    field_pos: FieldPos,
-) {
-    let asset: Handle<Texture> = asset_server.load("pic/upgrade_live.png");
+    assets: &mut GameAssets,
+    materials: &mut Assets<ColorMaterial>,
+    registry: &BlockRegistry,
+    playfield: &Playfield,
+    brick: &Brick,
+) -> Option<Entity> {
+    let field_pos = (brick.position.0 as i32, brick.position.1 as i32);
+    construct_block(commands, assets, materials, registry, playfield, field_pos, brick.block())
+}
 
-    let xy = field_pos_to_transform(field_pos);
-    commands
+/// Spawns `block` as described by its type in the registry, `None` for
+/// unknown types.
+pub fn construct_block(
+    commands: &mut Commands,
+    assets: &mut GameAssets,
+    materials: &mut Assets<ColorMaterial>,
+    registry: &BlockRegistry,
+    playfield: &Playfield,
+    field_pos: (i32, i32),
+    block: Block,
+) -> Option<Entity> {
+    let def = registry.get(&block.kind)?;
+    let xy = playfield.cell_center(field_pos);
+    let size = playfield.cell_size;
+    let material = assets.block(&block.kind, def, materials);
+    let label = def.label.clone().unwrap_or_else(|| block.health.to_string());
+    let (body, layer) = if def.sensor {
+        (RigidBody::Sensor, CollisionLayer::BlockSensor)
+    } else {
+        (RigidBody::Static, CollisionLayer::Block)
+    };
+    let label_offset = def.shape.label_offset(size);
+    let entity = commands
         .spawn_bundle(SpriteBundle {
-            material: materials.add(asset.into()),
-            transform: Transform::from_xyz(xy.0, xy.1, 0.),
-            sprite: Sprite::new(Vec2::new(CONFIG.block_size, CONFIG.block_size)),
+            material,
+            transform: Transform::from_xyz(xy.x, xy.y, 0.),
+            sprite: Sprite::new(Vec2::new(size, size)),
             ..Default::default()
-        }).with_children(|parent| {
+        })
+        .with_children(|parent| {
             parent.spawn_bundle(Text2dBundle {
                 draw: Draw {
                     ..Default::default()
@@ -96,16 +98,15 @@ pub fn construct_block_add_ball(
                     ..Default::default()
                 },
                 text: Text::with_section(
-                    // Accepts a `String` or any type that converts into a `String`, such as `&str`
-                    "+1".to_string(),
+                    label,
                     TextStyle {
-                        font: asset_server.load("fonts/Uroob-Regular.ttf"),
-                        font_size: CONFIG.block_size / 3. + 10.,
+                        font: assets.font.clone(),
+                        font_size: size / 3. + 10.,
                         color: Color::BLACK,
                     },
                     Default::default(),
                 ),
-                transform: Transform::from_xyz(CONFIG.block_size/10., -CONFIG.block_size / 3., 0.1),
+                transform: Transform::from_xyz(size / 10. + label_offset.x, -size / 3. + label_offset.y, 0.1),
                 global_transform: Default::default(),
                 main_pass: MainPass {},
                 text_2d_size: Text2dSize {
@@ -113,59 +114,108 @@ pub fn construct_block_add_ball(
                 },
             });
         })
-        .insert(RigidBody::Sensor)
-        .insert(CollisionShape::Cuboid {
-            half_extends: Vec3::new(CONFIG.block_size / 2., CONFIG.block_size / 2.,0.),
-            border_radius: None,
-        })  
+        .insert(body)
+        .insert(def.shape.collider(size, def.sensor))
         .insert(PhysicMaterial {
             restitution: 1.,
             ..Default::default()
         })
         .insert(
             CollisionLayers::none()
-                .with_group(CollisionLayer::BlockAddBall)
+                .with_group(layer)
                 .with_mask(CollisionLayer::Ball),
         )
-        .insert(Block::AddBall);
-        
+        .insert(block)
+        .insert(GridPos(field_pos.0, field_pos.1))
+        .id();
+    Some(entity)
 }
+
+/// Spawns a ball at `position` flying with `velocity`.
 pub fn construct_ball(
     commands: &mut Commands,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    asset_server: &mut ResMut<AssetServer>,
-
-    mouse_pos: Res<MousePos>,
-) {
-    let asset: Handle<Texture> = asset_server.load("pic/ball.png");
+    assets: &GameAssets,
+    position: Vec2,
+    velocity: Vec2,
+) -> Entity {
     commands
         .spawn_bundle(SpriteBundle {
-            material: materials.add(asset.into()),
-            transform: Transform::from_xyz(0.0, -CONFIG.window_height / 2., 1.0),
+            material: assets.ball.clone(),
+            transform: Transform::from_xyz(position.x, position.y, 1.0),
             sprite: Sprite::new(Vec2::new(CONFIG.ball_size, CONFIG.ball_size)),
             ..Default::default()    
         })
         .insert(Ball)
+        .insert(BallAge::default())
         .insert(RigidBody::Dynamic)
         .insert(CollisionShape::Sphere { radius: CONFIG.ball_size })
         .insert(PhysicMaterial {
             restitution: 1.,
+            friction: 0.,
             ..Default::default()
         })
         .insert(
             CollisionLayers::none()
                 .with_group(CollisionLayer::Ball)
-                .with_masks(vec![CollisionLayer::BlockStandard, CollisionLayer::BlockAddBall]),
+                .with_masks(vec![
+                    CollisionLayer::Block,
+                    CollisionLayer::BlockSensor,
+                    CollisionLayer::Wall,
+                    CollisionLayer::Floor,
+                ]),
         )
-        .insert(Velocity::from(direction_ball_to_mouse(*mouse_pos) * CONFIG.ballspeed));
+        .insert(Velocity::from(velocity))
+        .id()
 }
 
-fn field_pos_to_transform(field_pos: FieldPos) -> (f32, f32) {
-    //offset in blocks
-    let offset = 5;
-    let x =
-        field_pos.0 as f32 * CONFIG.block_size - CONFIG.window_width / 2. + CONFIG.block_size / 2.;
-    let y =
-        field_pos.1 as f32 * CONFIG.block_size - CONFIG.window_height / 2. - CONFIG.block_size / 2. + CONFIG.block_size * offset as f32;
-    (x, y)
-}
\ No newline at end of file
+/// Spawns the left, right and top walls around the playfield and the sensor
+/// floor below the launch line.
+pub fn construct_walls(commands: &mut Commands, playfield: &Playfield) {
+    let (min, max, center) = (playfield.min(), playfield.max(), playfield.center());
+    let half = playfield.size() / 2.;
+    let thickness = playfield.cell_size;
+    let walls = [
+        (Vec2::new(min.x - thickness / 2., center.y), Vec2::new(thickness / 2., half.y + thickness)),
+        (Vec2::new(max.x + thickness / 2., center.y), Vec2::new(thickness / 2., half.y + thickness)),
+        (Vec2::new(center.x, max.y + thickness / 2.), Vec2::new(half.x + thickness, thickness / 2.)),
+    ];
+    for (center, half_extends) in walls.iter() {
+        commands
+            .spawn()
+            .insert(Transform::from_xyz(center.x, center.y, 0.))
+            .insert(GlobalTransform::default())
+            .insert(RigidBody::Static)
+            .insert(CollisionShape::Cuboid {
+                half_extends: half_extends.extend(0.),
+                border_radius: None,
+            })
+            .insert(PhysicMaterial {
+                restitution: 1.,
+                friction: 0.,
+                ..Default::default()
+            })
+            .insert(
+                CollisionLayers::none()
+                    .with_group(CollisionLayer::Wall)
+                    .with_mask(CollisionLayer::Ball),
+            );
+    }
+    // the floor starts just below a ball resting on the launch line, so
+    // freshly launched balls don't touch it
+    let floor_top = min.y - CONFIG.ball_size - 1.;
+    commands
+        .spawn()
+        .insert(Transform::from_xyz(center.x, floor_top - thickness, 0.))
+        .insert(GlobalTransform::default())
+        .insert(RigidBody::Sensor)
+        .insert(CollisionShape::Cuboid {
+            half_extends: Vec3::new(half.x + thickness, thickness, 0.),
+            border_radius: None,
+        })
+        .insert(
+            CollisionLayers::none()
+                .with_group(CollisionLayer::Floor)
+                .with_mask(CollisionLayer::Ball),
+        )
+        .insert(Floor);
+}
@@ -3,6 +3,8 @@ use heron::*;
 #[derive(PhysicsLayer)]
 pub enum CollisionLayer {
     Ball,
-    BlockStandard,
-    BlockAddBall,
+    Block,
+    BlockSensor,
+    Wall,
+    Floor,
 }
\ No newline at end of file
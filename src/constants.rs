@@ -2,6 +2,8 @@ use std::fs::File;
 
 use ron::de::from_reader;
 
+use crate::resource::GameMode;
+
 pub const CONFIGPATH: &str = "assets/config/config.ron";
 
 lazy_static! {
@@ -12,8 +14,68 @@ lazy_static! {
 pub struct ConstantsManager {
     pub block_size: f32,
     pub ball_size: f32,
+    /// Initial window size, the camera fits the playfield into any size.
     pub window_width: f32,
     pub window_height: f32,
+    /// Playfield size in blocks, the walls enclose exactly this area.
+    #[serde(default = "columns")]
+    pub columns: usize,
+    #[serde(default = "rows")]
+    pub rows: usize,
+    /// Ball speed in units per second, kept constant during flight.
     pub ballspeed: f32,
-    
+    /// Flattest angle in degrees above the floor balls can be fired at.
+    #[serde(default = "min_launch_angle")]
+    pub min_launch_angle: f32,
+    /// Fixed level seed, a random one is rolled for every game if unset.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Level file below `assets/` to play instead of a generated map.
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default = "classic")]
+    pub mode: GameMode,
+    /// Vertical speed below which a ball counts as bouncing flat.
+    #[serde(default = "stuck_vertical_speed")]
+    pub stuck_vertical_speed: f32,
+    /// Seconds a ball may bounce flat before it gets nudged.
+    #[serde(default = "stuck_time")]
+    pub stuck_time: f32,
+    /// Degrees below the horizontal a nudged ball is turned to.
+    #[serde(default = "stuck_nudge_angle")]
+    pub stuck_nudge_angle: f32,
+    /// Seconds after which a ball is retired no matter what.
+    #[serde(default = "ball_max_lifetime")]
+    pub ball_max_lifetime: f32,
+    /// Seconds the blocks take to move down a row between turns.
+    #[serde(default = "block_descent_time")]
+    pub block_descent_time: f32,
+}
+
+fn classic() -> GameMode {
+    GameMode::Classic
+}
+fn columns() -> usize {
+    15
+}
+fn rows() -> usize {
+    15
+}
+fn min_launch_angle() -> f32 {
+    10.
+}
+fn stuck_vertical_speed() -> f32 {
+    20.
+}
+fn stuck_time() -> f32 {
+    1.
+}
+fn stuck_nudge_angle() -> f32 {
+    15.
+}
+fn ball_max_lifetime() -> f32 {
+    30.
+}
+fn block_descent_time() -> f32 {
+    0.25
 }
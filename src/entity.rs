@@ -1,11 +1,93 @@
+use bevy::prelude::{Entity, Vec2};
+
 
 #[derive(Debug)]
 pub struct Ball;
+/// Seconds a ball has been flying, and bouncing flat.
+#[derive(Debug, Default)]
+pub struct BallAge {
+    pub lifetime: f32,
+    pub flat: f32,
+}
+#[derive(Debug, Default)]
 pub struct Scoreboard {
     pub score: usize,
+    /// Turns survived in the current game.
+    pub turns: u32,
+}
+impl Scoreboard {
+    pub const HIT_POINTS: usize = 1;
+    pub const DESTROY_POINTS: usize = 10;
+}
+/// In-game score and turn display.
+pub struct Hud;
+/// Field position of a block, moved along with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridPos(pub i32, pub i32);
+/// Flash shown where a bomb went off or a laser fired, gone after
+/// `Burst::DURATION` seconds.
+#[derive(Debug, Default)]
+pub struct Burst {
+    pub age: f32,
+}
+impl Burst {
+    pub const DURATION: f32 = 0.3;
+}
+/// Short flash and swell of a block that lost health. `flash` is the child
+/// sprite drawn over the block until the pulse is over.
+#[derive(Debug)]
+pub struct HitPulse {
+    pub age: f32,
+    pub flash: Entity,
+}
+impl HitPulse {
+    pub const DURATION: f32 = 0.15;
 }
+/// Piece of a destroyed block flying apart, gone after `Shard::DURATION`
+/// seconds.
 #[derive(Debug)]
-pub enum Block {
-    Standard(u32),
-    AddBall,
-}
\ No newline at end of file
+pub struct Shard {
+    pub age: f32,
+    pub velocity: Vec2,
+    /// Radians per second.
+    pub spin: f32,
+}
+impl Shard {
+    pub const DURATION: f32 = 0.4;
+}
+/// Brick on the field, `kind` names its type in the `BlockRegistry`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Block {
+    pub kind: String,
+    pub health: u32,
+}
+impl Block {
+    pub fn new(kind: &str, health: u32) -> Self {
+        Block {
+            kind: kind.to_string(),
+            health,
+        }
+    }
+}
+/// Text showing the name typed for a new high score.
+pub struct NameEntry;
+pub struct HighScoreScreen;
+/// Init screen button resuming the saved game.
+pub struct ContinueButton;
+/// Dot of the aim preview line.
+pub struct AimDot;
+/// Highlights the block the aim preview runs into.
+pub struct AimTarget;
+/// Shows where the next turn's balls start.
+pub struct LaunchMarker;
+/// Sensor below the launch line, balls touching it are done for the turn.
+pub struct Floor;
+/// Extra ball from a splitter, it is not part of the shooter's count and
+/// does not move the launch point.
+pub struct SplitBall;
+/// Ball flying back to the launch point, it no longer hits anything.
+pub struct Recalled;
+/// Buttons shown while shooting.
+pub struct ShootingControls;
+pub struct RecallButton;
+pub struct FastForwardButton;
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use bevy::asset::{HandleId, LoadState};
+use bevy::prelude::*;
+
+use crate::GameState;
+use crate::blocktype::{BlockDef, BlockRegistry};
+use crate::level::SelectedLevel;
+
+pub const FONT: &str = "fonts/Uroob-Regular.ttf";
+pub const BALL_TEXTURE: &str = "pic/ball.png";
+
+/// Handles shared by everything spawned in game, loaded once at startup
+/// instead of on every spawn.
+pub struct GameAssets {
+    asset_server: AssetServer,
+    pub font: Handle<Font>,
+    /// Loaded textures by their path below `assets/`.
+    textures: HashMap<String, Handle<Texture>>,
+    pub ball: Handle<ColorMaterial>,
+    pub button: Handle<ColorMaterial>,
+    pub continue_button: Handle<ColorMaterial>,
+    pub aim_dot: Handle<ColorMaterial>,
+    pub aim_target: Handle<ColorMaterial>,
+    pub explosion: Handle<ColorMaterial>,
+    pub laser_beam: Handle<ColorMaterial>,
+    /// Unshaded material of every block type spawned so far, by kind.
+    blocks: HashMap<String, Handle<ColorMaterial>>,
+}
+impl GameAssets {
+    pub fn texture(&mut self, path: &str) -> Handle<Texture> {
+        let asset_server = &self.asset_server;
+        self.textures
+            .entry(path.to_string())
+            .or_insert_with(|| asset_server.load(path))
+            .clone()
+    }
+
+    /// Unshaded material of block type `kind`. Built on first use, so types
+    /// added to the registry after startup spawn as well.
+    pub fn block(&mut self, kind: &str, def: &BlockDef, materials: &mut Assets<ColorMaterial>) -> Handle<ColorMaterial> {
+        if let Some(material) = self.blocks.get(kind) {
+            return material.clone();
+        }
+        let texture = match &def.sprite {
+            Some(sprite) => Some(self.texture(sprite)),
+            None => def.shape.texture(),
+        };
+        let material = materials.add(match texture {
+            Some(texture) => ColorMaterial::modulated_texture(texture, def.color()),
+            None => def.color().into(),
+        });
+        self.blocks.insert(kind.to_string(), material.clone());
+        material
+    }
+
+    /// Everything loaded from disk, the game waits for these in
+    /// `GameState::Loading`.
+    pub fn loaded_ids(&self) -> impl Iterator<Item = HandleId> + '_ {
+        std::iter::once(self.font.id).chain(self.textures.values().map(|texture| texture.id))
+    }
+}
+impl FromWorld for GameAssets {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+        let asset_server = world.get_resource::<AssetServer>().unwrap().clone();
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        let registry = world.get_resource::<BlockRegistry>().unwrap();
+
+        let ball_texture: Handle<Texture> = asset_server.load(BALL_TEXTURE);
+        let mut assets = GameAssets {
+            font: asset_server.load(FONT),
+            textures: std::iter::once((BALL_TEXTURE.to_string(), ball_texture.clone())).collect(),
+            ball: materials.add(ball_texture.into()),
+            button: materials.add(Color::rgb(0.1, 0.5, 0.3).into()),
+            continue_button: materials.add(Color::rgb(0.1, 0.3, 0.5).into()),
+            aim_dot: materials.add(Color::rgb(0.1, 0.5, 0.3).into()),
+            aim_target: materials.add(Color::rgba(0.1, 0.5, 0.3, 0.4).into()),
+            explosion: materials.add(Color::rgba(1., 0.6, 0.1, 0.6).into()),
+            laser_beam: materials.add(Color::rgba(1., 0.95, 0.3, 0.6).into()),
+            blocks: HashMap::new(),
+            asset_server,
+        };
+        // the known types up front, so loading waits for their sprites
+        for (kind, def) in registry.types.iter() {
+            assets.block(kind, def, &mut materials);
+        }
+        assets
+    }
+}
+
+/// Shows the menu once the shared assets and the selected level are in.
+/// Missing files don't hold the game up, they just stay invisible.
+pub fn loading_system(
+    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    selected_level: Res<SelectedLevel>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let ids = game_assets.loaded_ids().chain(selected_level.handle.iter().map(|level| level.id));
+    let mut failed = 0;
+    for id in ids {
+        match asset_server.get_load_state(id) {
+            LoadState::Loaded => {},
+            LoadState::Failed => failed += 1,
+            LoadState::NotLoaded | LoadState::Loading => return,
+        }
+    }
+    if failed > 0 {
+        warn!("{} assets failed to load", failed);
+    }
+    let _ = game_state.set(GameState::Init);
+}
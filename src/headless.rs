@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use bevy::asset::AssetPlugin;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::transform::TransformPlugin;
+use bevy::window::WindowPlugin;
+use heron::PhysicsSteps;
+
+use crate::BrickgamePlugin;
+use crate::resource::{GameRng, LevelSeed, SimulationStep};
+
+/// Seconds every headless frame advances the game and the physics.
+pub const HEADLESS_STEP: f32 = 1. / 60.;
+
+/// Builds the game without window or renderer. Every `update` advances by
+/// `HEADLESS_STEP` and all randomness comes from `seed`, so a round plays out
+/// the same way each time it is stepped.
+pub fn headless_app(seed: u64) -> AppBuilder {
+    let mut app = App::build();
+    app
+        .add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin::default())
+        .add_plugin(InputPlugin::default())
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            exit_on_close: false,
+        })
+        .add_plugin(AssetPlugin::default())
+        // normally registered by the render, sprite and text plugins
+        .add_asset::<Texture>()
+        .add_asset::<ColorMaterial>()
+        .add_asset::<Font>()
+        .insert_resource(SimulationStep(Some(HEADLESS_STEP)))
+        .insert_resource(PhysicsSteps::every_frame(Duration::from_secs_f32(HEADLESS_STEP)))
+        .insert_resource(GameRng::from_seed(seed))
+        .insert_resource(LevelSeed::fixed(seed))
+        .add_plugin(BrickgamePlugin);
+    app
+}
@@ -0,0 +1,329 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use ron::de::from_reader;
+use ron::ser::{PrettyConfig, to_string_pretty};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{HighScoreScreen, NameEntry, Scoreboard};
+use crate::gameassets::GameAssets;
+use crate::level::{Level, SelectedLevel};
+use crate::resource::{GameMode, HasWon, LevelSeed};
+
+/// Entries kept per table.
+pub const TOP_N: usize = 10;
+/// Tables of generated boards kept, every random game adds one.
+pub const MAX_SEED_TABLES: usize = 50;
+const MAX_NAME_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: usize,
+    pub turns: u32,
+}
+
+/// What a table is kept for: a generated map by its seed, a hand-authored
+/// level by its path, whatever seed it was played with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Board {
+    Seed(u64),
+    Level(String),
+}
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Board::Seed(seed) => write!(f, "seed {}", seed),
+            Board::Level(path) => write!(f, "level {}", path),
+        }
+    }
+}
+
+/// Best runs of one board.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    pub mode: GameMode,
+    pub board: Board,
+    pub entries: Vec<HighScoreEntry>,
+}
+
+/// All high score tables, stored as RON in the user's data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub tables: Vec<HighScoreTable>,
+    /// Where the tables are saved, nothing is written without one.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl HighScores {
+    /// `highscores.ron` in the platform data directory, e.g.
+    /// `$XDG_DATA_HOME/brickgame` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("brickgame").join("highscores.ron"))
+    }
+
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut high_scores: HighScores = path
+            .as_ref()
+            .and_then(|path| File::open(path).ok())
+            .and_then(|file| match from_reader(file) {
+                Ok(high_scores) => Some(high_scores),
+                Err(e) => {
+                    warn!("ignoring unreadable high scores: {}", e);
+                    None
+                },
+            })
+            .unwrap_or_default();
+        high_scores.path = path;
+        high_scores
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let ron = to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        fs::write(path, ron)
+    }
+
+    pub fn table(&self, mode: GameMode, board: &Board) -> Option<&HighScoreTable> {
+        self.tables
+            .iter()
+            .find(|table| table.mode == mode && table.board == *board)
+    }
+
+    /// Whether `score` would make it into the table of the board.
+    pub fn qualifies(&self, mode: GameMode, board: &Board, score: usize) -> bool {
+        if score == 0 {
+            return false;
+        }
+        match self.table(mode, board) {
+            Some(table) => {
+                table.entries.len() < TOP_N
+                    || table.entries.last().map_or(true, |last| last.score < score)
+            },
+            None => true,
+        }
+    }
+
+    /// Adds `entry` to the table of the board. Tables are kept from least to
+    /// most recently played, beyond `MAX_SEED_TABLES` the oldest seed tables
+    /// are dropped.
+    pub fn insert(&mut self, mode: GameMode, board: Board, entry: HighScoreEntry) {
+        let mut table = match self
+            .tables
+            .iter()
+            .position(|table| table.mode == mode && table.board == board)
+        {
+            Some(index) => self.tables.remove(index),
+            None => HighScoreTable { mode, board, entries: Vec::new() },
+        };
+        table.entries.push(entry);
+        table.entries.sort_by(|a, b| b.score.cmp(&a.score));
+        table.entries.truncate(TOP_N);
+        self.tables.push(table);
+
+        let seed_tables = self.tables.iter().filter(|table| matches!(table.board, Board::Seed(_))).count();
+        let mut excess = seed_tables.saturating_sub(MAX_SEED_TABLES);
+        self.tables.retain(|table| {
+            let drop = excess > 0 && matches!(table.board, Board::Seed(_));
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+}
+
+/// Finished run waiting for the player's name.
+#[derive(Debug, Clone)]
+pub struct PendingHighScore {
+    pub mode: GameMode,
+    pub board: Board,
+    pub entry: HighScoreEntry,
+}
+
+/// Board of the last finished game. The menu already rolled the seed of the
+/// next one, so the high score screen shows this board's table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastBoard {
+    pub mode: GameMode,
+    pub board: Board,
+}
+
+/// Settings deciding which board is played.
+#[derive(SystemParam)]
+pub struct PlayedBoard<'a> {
+    pub game_mode: Res<'a, GameMode>,
+    pub level_seed: Res<'a, LevelSeed>,
+    pub selected_level: Res<'a, SelectedLevel>,
+    pub levels: Res<'a, Assets<Level>>,
+}
+impl<'a> PlayedBoard<'a> {
+    /// The selected level if it loaded, else the map generated from the seed.
+    pub fn current(&self) -> LastBoard {
+        let level = self.selected_level.handle.as_ref().and_then(|handle| self.levels.get(handle));
+        let board = match (&self.selected_level.path, level) {
+            (Some(path), Some(_)) => Board::Level(path.clone()),
+            _ => Board::Seed(self.level_seed.seed),
+        };
+        LastBoard { mode: *self.game_mode, board }
+    }
+}
+
+fn save_pending(high_scores: &mut HighScores, pending: PendingHighScore) {
+    let mut entry = pending.entry;
+    if entry.name.is_empty() {
+        entry.name = "anonymous".to_string();
+    }
+    high_scores.insert(pending.mode, pending.board, entry);
+    if let Err(e) = high_scores.save() {
+        warn!("could not save high scores: {}", e);
+    }
+}
+
+/// Has to run before the seed of the next game is rolled.
+pub fn record_high_score_system(
+    has_won: Res<HasWon>,
+    scoreboard: Res<Scoreboard>,
+    played: PlayedBoard,
+    high_scores: Res<HighScores>,
+    mut pending: ResMut<Option<PendingHighScore>>,
+    mut last_board: ResMut<Option<LastBoard>>,
+) {
+    if has_won.is_none() {
+        return;
+    }
+    let LastBoard { mode, board } = played.current();
+    *last_board = Some(LastBoard { mode, board: board.clone() });
+    if !high_scores.qualifies(mode, &board, scoreboard.score) {
+        return;
+    }
+    *pending = Some(PendingHighScore {
+        mode,
+        board,
+        entry: HighScoreEntry {
+            name: String::new(),
+            score: scoreboard.score,
+            turns: scoreboard.turns,
+        },
+    });
+}
+
+pub fn name_entry_system(
+    mut char_events: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut pending: ResMut<Option<PendingHighScore>>,
+    mut high_scores: ResMut<HighScores>,
+    mut name_query: Query<&mut Text, With<NameEntry>>,
+) {
+    let name = match pending.as_mut() {
+        Some(pending) => &mut pending.entry.name,
+        None => return,
+    };
+    for event in char_events.iter() {
+        if event.char.is_alphanumeric() && name.chars().count() < MAX_NAME_LEN {
+            name.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    for mut text in name_query.iter_mut() {
+        text.sections[0].value = name_entry_text(name);
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        if let Some(pending) = pending.take() {
+            save_pending(&mut high_scores, pending);
+        }
+        for mut text in name_query.iter_mut() {
+            text.sections[0].value = "high score saved".to_string();
+        }
+    }
+}
+
+/// Starting the next game without confirming a name still keeps the score.
+pub fn save_pending_high_score_system(
+    mut pending: ResMut<Option<PendingHighScore>>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    if let Some(pending) = pending.take() {
+        save_pending(&mut high_scores, pending);
+    }
+}
+
+pub fn name_entry_text(name: &str) -> String {
+    format!("new high score! name: {}_", name)
+}
+
+pub fn high_score_screen_setup_system(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    high_scores: Res<HighScores>,
+    played: PlayedBoard,
+    last_board: Res<Option<LastBoard>>,
+) {
+    let font = assets.font.clone();
+    // before the first game, the board about to be played
+    let LastBoard { mode, board } = last_board.clone().unwrap_or_else(|| played.current());
+    let mut lines = vec![
+        format!("high scores {:?} {}", mode, board),
+    ];
+    match high_scores.table(mode, &board) {
+        Some(table) => lines.extend(table.entries.iter().enumerate().map(|(rank, entry)| {
+            format!("{}. {}  {} ({} turns)", rank + 1, entry.name, entry.score, entry.turns)
+        })),
+        None => lines.push("no runs on this board yet".to_string()),
+    }
+    lines.push("back with escape".to_string());
+
+    commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            // center the list
+            margin: Rect::all(Val::Auto),
+            // stack the lines top to bottom
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: assets.button.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        for line in lines {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    line,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        }
+    })
+    .insert(HighScoreScreen);
+}
+
+pub fn despawn_high_score_screen_system(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<HighScoreScreen>>,
+) {
+    screen_query
+    .iter()
+    .for_each(|e| commands.entity(e).despawn_recursive());
+}
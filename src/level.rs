@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
+
+use crate::builder::FieldPos;
+use crate::constants::CONFIG;
+use crate::entity::Block;
+use crate::resource::Playfield;
+
+/// Hand-authored board, loaded from `assets/levels/*.level`.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "6c1a1f7e-3b0f-4a55-9d0e-2f1b8f3c7a41"]
+pub struct Level {
+    pub width: usize,
+    pub height: usize,
+    /// Balls the shooter starts with.
+    pub balls: u32,
+    /// Rows generated on top after every turn in endless mode.
+    #[serde(default = "one_row")]
+    pub rows_per_turn: usize,
+    pub bricks: Vec<Brick>,
+}
+
+/// Block placed on the board before the first turn.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Brick {
+    pub position: FieldPos,
+    /// Name of the block type in `blocks.ron`.
+    pub kind: String,
+    #[serde(default = "one")]
+    pub health: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn one_row() -> usize {
+    1
+}
+
+impl Brick {
+    pub fn new(position: FieldPos, kind: &str, health: u32) -> Self {
+        Brick {
+            position,
+            kind: kind.to_string(),
+            health,
+        }
+    }
+
+    pub fn block(&self) -> Block {
+        Block {
+            kind: self.kind.clone(),
+            health: self.health,
+        }
+    }
+}
+
+impl Level {
+    /// Bricks inside both the declared grid and the walls of `playfield`, the
+    /// rest could never be hit and is reported and dropped.
+    pub fn bricks(&self, playfield: &Playfield) -> Vec<Brick> {
+        let width = self.width.min(playfield.columns);
+        let height = self.height.min(playfield.top_row() + 1);
+        self.bricks
+            .iter()
+            .filter(|brick| {
+                let inside = brick.position.0 < width && brick.position.1 < height;
+                if !inside {
+                    warn!("brick at {:?} is outside the {}x{} board", brick.position, width, height);
+                }
+                inside
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Level file picked in the config or on the command line, played instead of
+/// a generated map.
+#[derive(Clone)]
+pub struct SelectedLevel {
+    pub path: Option<String>,
+    pub handle: Option<Handle<Level>>,
+}
+impl Default for SelectedLevel {
+    fn default() -> Self {
+        SelectedLevel {
+            path: CONFIG.level.clone(),
+            handle: None,
+        }
+    }
+}
+impl SelectedLevel {
+    pub fn new(path: &str) -> Self {
+        SelectedLevel {
+            path: Some(path.to_string()),
+            handle: None,
+        }
+    }
+}
+
+pub fn load_level_system(
+    asset_server: Res<AssetServer>,
+    mut selected_level: ResMut<SelectedLevel>,
+) {
+    if let Some(path) = selected_level.path.clone() {
+        selected_level.handle = Some(asset_server.load(path.as_str()));
+    }
+}
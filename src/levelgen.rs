@@ -0,0 +1,74 @@
+use rand::Rng;
+
+use crate::builder::FieldPos;
+use crate::blocktype::{ADD_BALL, BOMB, CIRCLE, LASER_COLUMN, LASER_ROW, STANDARD, TRIANGLES};
+use crate::level::Brick;
+
+/// Seeded voronoi map generator. Scatters sites over the field, gives every
+/// site a brick type and fills each cell with the type of its nearest site.
+/// The same rng state always yields the same map.
+pub fn voronoi_map_gen(dimensions: (usize, usize), rng: &mut impl Rng) -> Vec<Brick> {
+    let (width, height) = dimensions;
+    let site_count = (width * height / 8).max(1);
+    // `None` sites leave their region empty
+    let sites: Vec<(FieldPos, Option<(&str, u32)>)> = (0..site_count)
+        .map(|_| {
+            let position = (rng.gen_range(0..width), rng.gen_range(0..height));
+            let brick_type = match rng.gen_range(0..20) {
+                0 => Some((ADD_BALL, 1)),
+                1 => Some((BOMB, rng.gen_range(1..=3))),
+                2 => Some((LASER_ROW, 1)),
+                3 => Some((LASER_COLUMN, 1)),
+                4..=8 => None,
+                9 => Some((CIRCLE, rng.gen_range(1..=10))),
+                10 => Some((TRIANGLES[rng.gen_range(0..TRIANGLES.len())], rng.gen_range(1..=10))),
+                _ => Some((STANDARD, rng.gen_range(1..=10))),
+            };
+            (position, brick_type)
+        })
+        .collect();
+
+    let mut bricks = Vec::new();
+    for x in 0..width {
+        for y in 0..height {
+            let (site_pos, site_type) = sites
+                .iter()
+                .min_by_key(|(pos, _)| distance_squared((x, y), *pos))
+                .unwrap();
+            let (kind, health) = match *site_type {
+                // a whole region of specials would be too generous
+                Some((kind, _)) if !fills_region(kind) && *site_pos != (x, y) => continue,
+                Some(brick_type) => brick_type,
+                None => continue,
+            };
+            bricks.push(Brick::new((x, y), kind, health));
+        }
+    }
+    bricks
+}
+
+/// Plain damageable bricks, placed over the whole region of their site.
+fn fills_region(kind: &str) -> bool {
+    kind == STANDARD || kind == CIRCLE || TRIANGLES.contains(&kind)
+}
+
+fn distance_squared(a: FieldPos, b: FieldPos) -> usize {
+    let dx = if a.0 > b.0 { a.0 - b.0 } else { b.0 - a.0 };
+    let dy = if a.1 > b.1 { a.1 - b.1 } else { b.1 - a.1 };
+    dx * dx + dy * dy
+}
+
+/// One top row of the endless mode, bricks get tougher the more turns were
+/// played. Every row carries exactly one pickup.
+pub fn row_gen(width: usize, row: usize, turn: u32, rng: &mut impl Rng) -> Vec<Brick> {
+    let turn = turn.max(1);
+    let add_ball = rng.gen_range(0..width);
+    let mut bricks = vec![Brick::new((add_ball, row), ADD_BALL, 1)];
+    for x in (0..width).filter(|x| *x != add_ball) {
+        if rng.gen_bool(0.5) {
+            let health = rng.gen_range(turn..=turn * 2);
+            bricks.push(Brick::new((x, row), STANDARD, health));
+        }
+    }
+    bricks
+}
@@ -0,0 +1,539 @@
+use bevy::prelude::*;
+pub mod constants;
+pub mod entity;
+pub mod builder;
+pub mod resource;
+pub mod headless;
+pub mod levelgen;
+pub mod level;
+pub mod highscore;
+pub mod savegame;
+pub mod aim;
+pub mod blocktype;
+pub mod gameassets;
+use bevy_asset_ron::RonAssetPlugin;
+use builder::{BallSpawner, BlockSpawner, construct_walls};
+use entity::*;
+pub mod components;
+use blocktype::{BlockPalette, BlockRegistry, shape_texture_setup_system};
+use gameassets::{GameAssets, loading_system};
+use constants::CONFIG;
+use heron::PhysicsPlugin;
+use resource::{BallSpawnTimer, BlockDescent, GameMode, GameProgress, GameRng, GameSpeed, HasWon, LaunchPoint, LevelSeed, MousePos, Playfield, Shooter, SimulationClock, SimulationStep};
+use system::{Contact, ContactSource, Explosion, GameEvents, Laser, physics_contact_system, advance_launch_point_system, burst_system, explosion_system, hit_pulse_system, laser_system, shard_system, camera_scale_system, constant_speed_system, button_system, descend_blocks_system, check_blocks_system, collision_events, despawn_balls_system, despawn_blocks_system, despawn_button_system, despawn_hud_system, despawn_launch_marker_system, despawn_shooting_controls_system, end_round_system, fast_forward_system, floor_collision_system, mouse_listener_system, move_blocks_system, read_game_events, recall_system, reset_game_speed_system, spawn_rows_system, stuck_ball_system, update_block_text, update_hud_system, update_launch_marker_system};
+use level::{Level, SelectedLevel, load_level_system};
+use aim::{aim_preview_setup_system, aim_preview_system, despawn_aim_preview_system};
+use savegame::{AutosaveRequested, SaveGame, SaveSlot, SessionSettings, autosave_system, clear_finished_save_system, continue_system, end_continued_game_system, request_autosave_system, restore_game_system};
+use highscore::{HighScores, LastBoard, PendingHighScore, despawn_high_score_screen_system, high_score_screen_setup_system, name_entry_system, name_entry_text, record_high_score_system, save_pending_high_score_system};
+pub mod system;
+
+#[macro_use]
+extern crate lazy_static;
+
+/// Everything the game needs besides windowing and rendering, so the same
+/// state machine runs both in `main` and in the headless app.
+pub struct BrickgamePlugin;
+
+impl Plugin for BrickgamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+        .add_plugin(PhysicsPlugin::default()) // Add the plugin
+        .add_plugin(RonAssetPlugin::<Level>::new(&["level"]))
+        .add_event::<GameEvents>()
+        .add_event::<Explosion>()
+        .add_event::<Laser>()
+        .add_event::<Contact>()
+        .insert_resource(HasWon::default())
+        .insert_resource(Scoreboard::default())
+        // letterbox, the board itself gets a lighter background
+        .insert_resource(ClearColor(Color::rgb(0.3, 0.3, 0.3)))
+        .insert_resource(Shooter::default())
+        .insert_resource(MousePos::ZERO)
+        .init_resource::<Playfield>()
+        .init_resource::<BlockRegistry>()
+        .init_resource::<ContactSource>()
+        .init_resource::<BlockPalette>()
+        .init_resource::<GameAssets>()
+        .init_resource::<LaunchPoint>()
+        .init_resource::<GameSpeed>()
+        .init_resource::<SimulationStep>()
+        .init_resource::<GameRng>()
+        .init_resource::<LevelSeed>()
+        .init_resource::<SelectedLevel>()
+        .init_resource::<GameMode>()
+        .init_resource::<HighScores>()
+        .insert_resource(Option::<PendingHighScore>::None)
+        .insert_resource(Option::<LastBoard>::None)
+        .init_resource::<SaveSlot>()
+        .init_resource::<AutosaveRequested>()
+        .insert_resource(Option::<SaveGame>::None)
+        .insert_resource(Option::<SessionSettings>::None)
+        .init_resource::<BallSpawnTimer>()
+        .init_resource::<BlockDescent>()
+        .add_system(physics_contact_system.system().label("contacts"))
+        .add_system(collision_events.system().label("collision_events").after("contacts").before("update_block_text"))
+        .add_system(update_block_text.system().label("update_block_text"))
+        .add_system(explosion_system.system().after("update_block_text"))
+        .add_system(laser_system.system())
+        .add_system(burst_system.system())
+        .add_system(hit_pulse_system.system())
+        .add_system(shard_system.system())
+        .add_system(update_hud_system.system())
+        .add_system(update_launch_marker_system.system())
+        .add_system(camera_scale_system.system())
+        .add_system_to_stage(CoreStage::PostUpdate, autosave_system.system())
+
+        // startup
+        .add_startup_system(camera_init_system.system())
+        .add_startup_system(load_level_system.system())
+        .add_startup_system(playfield_setup_system.system())
+        .add_startup_system(shape_texture_setup_system.system())
+        //.add_startup_system(physic_init_system.system())
+        .add_state(GameState::Loading)
+        .add_system(mouse_listener_system.system())
+        // Gamestate Loading
+        .add_system_set(
+            SystemSet::on_update(GameState::Loading)
+                .with_system(loading_system.system())
+        )
+        // Gamestate Init
+        .add_system_set(
+            SystemSet::on_enter(GameState::Init)
+                .with_system(despawn_blocks_system.system())
+                .with_system(despawn_hud_system.system())
+                .with_system(despawn_launch_marker_system.system())
+                .with_system(record_high_score_system.system().label("record_high_score"))
+                .with_system(end_continued_game_system.system().label("end_continued_game").after("record_high_score"))
+                .with_system(reroll_level_seed_system.system().label("reroll_seed").after("end_continued_game"))
+                .with_system(clear_finished_save_system.system().label("clear_save"))
+                .with_system(button_setup_system.system().after("reroll_seed").after("clear_save"))
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Init)
+                .with_system(button_system.system())
+                .with_system(name_entry_system.system())
+                .with_system(continue_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::Init)
+                .with_system(despawn_button_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_resume(GameState::Init)
+                .with_system(button_setup_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Init)
+                .with_system(save_pending_high_score_system.system())
+                .with_system(despawn_button_system.system())
+                .with_system(block_setup.system().label("block_setup"))
+                .with_system(restore_game_system.system().after("block_setup"))
+                .with_system(hud_setup_system.system())
+                .with_system(launch_marker_setup_system.system())
+        )
+        // Gamestate Aiming
+        .add_system_set(
+            SystemSet::on_enter(GameState::Aiming)
+                .with_system(request_autosave_system.system())
+                .with_system(aim_preview_setup_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Aiming)
+                .with_system(aim_preview_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Aiming)
+                .with_system(despawn_aim_preview_system.system())
+        )
+        // Gamestate HighScores
+        .add_system_set(
+            SystemSet::on_enter(GameState::HighScores)
+                .with_system(high_score_screen_setup_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::HighScores)
+                .with_system(despawn_high_score_screen_system.system())
+        )
+        // Gamestate Shooting
+
+        .add_system_set(
+            SystemSet::on_enter(GameState::Shooting)
+                .with_system(shooting_controls_setup_system.system())
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Shooting)
+                .with_system(end_round_system.system().label("end_round").before("retire_balls").before("ball_setup"))
+                .with_system(ball_setup.system().label("ball_setup"))
+                .with_system(recall_system.system().before("ball_setup"))
+                .with_system(stuck_ball_system.system().label("retire_balls"))
+                .with_system(constant_speed_system.system())
+                .with_system(fast_forward_system.system())
+                .with_system(read_game_events.system())
+                .with_system(check_blocks_system.system())
+                .with_system(floor_collision_system.system().label("retire_balls")),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Shooting)
+                .with_system(despawn_balls_system.system())
+                .with_system(advance_launch_point_system.system())
+                .with_system(reset_game_speed_system.system())
+                .with_system(despawn_shooting_controls_system.system())
+        )
+        // Gamestate MovingBlocks
+        .add_system_set(
+            SystemSet::on_enter(GameState::MovingBlocks)
+            .with_system(move_blocks_system.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::MovingBlocks)
+            .with_system(descend_blocks_system.system().chain(spawn_rows_system.system())),
+        );
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, serde::Serialize, serde::Deserialize)]
+pub enum GameState {
+    /// Waits for `GameAssets` before showing the menu.
+    Loading,
+    Init,
+    Shooting,
+    Aiming,
+    MovingBlocks,
+    HighScores,
+}
+
+
+fn block_setup(
+    mut spawner: BlockSpawner,
+    mut progress: GameProgress,
+    level_seed: Res<LevelSeed>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    game_mode: Res<GameMode>,
+    restore: Res<Option<SaveGame>>,
+) {
+    // a continued game brings its own board
+    if restore.is_some() {
+        return;
+    }
+    let playfield = *spawner.playfield;
+    *progress.game_rng = GameRng::from_seed(level_seed.seed);
+    *progress.shooter = Shooter::default();
+    *progress.scoreboard = Scoreboard::default();
+    *progress.launch_point = LaunchPoint::centered(&playfield);
+    let level = selected_level.handle.as_ref().and_then(|handle| levels.get(handle));
+    let bricks = match level {
+        Some(level) => {
+            progress.shooter.count = level.balls;
+            level.bricks(&playfield)
+        },
+        None => {
+            if let Some(path) = &selected_level.path {
+                warn!("level {} is not loaded, generating a map instead", path);
+            }
+            match *game_mode {
+                // only the rows up to `top_row` are inside the walls
+                GameMode::Classic => levelgen::voronoi_map_gen(
+                    (playfield.columns, playfield.top_row() + 1),
+                    &mut progress.game_rng.0,
+                ),
+                // endless games start with a single row
+                GameMode::Endless => levelgen::row_gen(playfield.columns, playfield.top_row(), 1, &mut progress.game_rng.0),
+            }
+        },
+    };
+    for brick in &bricks {
+        spawner.brick(brick);
+    }
+}
+
+fn reroll_level_seed_system(mut level_seed: ResMut<LevelSeed>) {
+    if !level_seed.fixed {
+        *level_seed = LevelSeed::random();
+    }
+}
+
+/// Launches the next ball of the round, only runs while shooting.
+fn ball_setup(
+    mut balls: BallSpawner,
+    mut shooter_count: ResMut<Shooter>,
+    mouse_pos: Res<MousePos>,
+    clock: SimulationClock,
+    mut spawn_timer: ResMut<BallSpawnTimer>,
+    launch_point: Res<LaunchPoint>,
+    playfield: Res<Playfield>,
+) {
+    if !spawn_timer.0.tick(clock.game_delta()).just_finished() {
+        return;
+    }
+    if !shooter_count.finished {
+        let launch = launch_point.position(&playfield);
+        let velocity = launch_direction(*mouse_pos, launch) * CONFIG.ballspeed;
+        balls.ball(launch, velocity);
+        shooter_count.shooted += 1;
+        if shooter_count.shooted == shooter_count.count {
+            shooter_count.shooted = 0;
+            shooter_count.finished = true;
+        }
+    }
+}
+
+fn playfield_setup_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    playfield: Res<Playfield>,
+) {
+    commands.spawn_bundle(SpriteBundle {
+        material: materials.add(Color::rgb(0.9, 0.9, 0.9).into()),
+        transform: Transform::from_translation(playfield.center().extend(-1.)),
+        sprite: Sprite::new(playfield.size()),
+        ..Default::default()
+    });
+    construct_walls(&mut commands, &playfield);
+}
+
+fn camera_init_system(mut commands: Commands, playfield: Res<Playfield>) {
+    // spawn camera, camera_scale_system fits it to the window
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.transform.translation = playfield.center().extend(camera.transform.translation.z);
+    commands.spawn_bundle(camera);
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
+
+fn direction_ball_to_mouse(mouse_pos: Vec2, launch: Vec2) -> Vec2 {
+    mouse_pos - launch
+}
+
+/// Unit direction balls are fired in, never flatter than
+/// `CONFIG.min_launch_angle` above the floor.
+pub fn launch_direction(mouse_pos: Vec2, launch: Vec2) -> Vec2 {
+    let direction = direction_ball_to_mouse(mouse_pos, launch);
+    let min_angle = CONFIG.min_launch_angle.to_radians();
+    let max_angle = std::f32::consts::PI - min_angle;
+    let angle = if direction.y <= 0. {
+        // below the launch point, fire along the floor on the aimed side
+        if direction.x < 0. { max_angle } else { min_angle }
+    } else {
+        direction.y.atan2(direction.x).max(min_angle).min(max_angle)
+    };
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+fn button_setup_system(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    has_won: Res<HasWon>,
+    level_seed: Res<LevelSeed>,
+    scoreboard: Res<Scoreboard>,
+    pending_high_score: Res<Option<PendingHighScore>>,
+    save_slot: Res<SaveSlot>,
+){
+    let size = Size::new(Val::Percent(50.), Val::Percent(100. / 6.));
+    let message = match *has_won {
+        None => "Init new Game with space or click!",
+        Some(true) => "you won. Init new Game with space or click!",
+        Some(false) => "you lost. Init new Game with space or click!",
+    };
+    println!("{}", message);
+    let font = assets.font.clone();
+    commands
+    .spawn_bundle(ButtonBundle {
+        style: Style {
+            size,
+            // center button
+            margin: Rect::all(Val::Auto),
+            // stack the texts top to bottom
+            flex_direction: FlexDirection::ColumnReverse,
+            // horizontally center child text
+            justify_content: JustifyContent::Center,
+            // vertically center child text
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: assets.button.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                message,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+        if has_won.is_some() {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    format!("score {} after {} turns", scoreboard.score, scoreboard.turns),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        }
+        if let Some(pending) = &*pending_high_score {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    name_entry_text(&pending.entry.name),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 25.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            })
+            .insert(NameEntry);
+        }
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                format!("seed {}  (h: high scores)", level_seed.seed),
+                TextStyle {
+                    font,
+                    font_size: 25.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+    });
+    if save_slot.exists() {
+        commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(25.), Val::Percent(100. / 16.)),
+                // center button
+                margin: Rect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.continue_button.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Continue with c or click",
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: 30.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        })
+        .insert(ContinueButton);
+    }
+}
+
+fn hud_setup_system(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+) {
+    commands
+    .spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(5.),
+                left: Val::Px(10.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: Text::with_section(
+            // filled in by update_hud_system
+            String::new(),
+            TextStyle {
+                font: assets.font.clone(),
+                font_size: 40.0,
+                color: Color::rgb(0.1, 0.5, 0.3),
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    })
+    .insert(Hud);
+}
+
+fn launch_marker_setup_system(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    launch_point: Res<LaunchPoint>,
+    playfield: Res<Playfield>,
+) {
+    commands
+    .spawn_bundle(SpriteBundle {
+        material: assets.ball.clone(),
+        transform: Transform::from_translation(launch_point.position(&playfield).extend(1.)),
+        sprite: Sprite::new(Vec2::new(CONFIG.ball_size * 1.5, CONFIG.ball_size * 1.5)),
+        ..Default::default()
+    })
+    .insert(LaunchMarker);
+}
+
+fn shooting_controls_setup_system(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+) {
+    let font = assets.font.clone();
+    let material = assets.button.clone();
+    let labels = ["recall (r)", "hold: fast (f)"];
+    for (index, label) in labels.iter().enumerate() {
+        let mut button = commands.spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100. / 6.), Val::Percent(5.)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(10.),
+                    right: Val::Percent(1. + index as f32 * (100. / 6. + 1.)),
+                    ..Default::default()
+                },
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: material.clone(),
+            ..Default::default()
+        });
+        button.with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    *label,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 25.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        })
+        .insert(ShootingControls);
+        if index == 0 {
+            button.insert(RecallButton);
+        } else {
+            button.insert(FastForwardButton);
+        }
+    }
+}
@@ -1,5 +1,15 @@
+use std::time::Duration;
+
+use bevy::core::{Time, Timer};
+use bevy::ecs::system::{Res, ResMut, SystemParam};
 use bevy::math::Vec2;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::constants::CONFIG;
+use crate::entity::Scoreboard;
 
+/// World position of the last click while aiming.
 pub type MousePos = Vec2;
 
 pub type HasWon = Option<bool>;
@@ -24,4 +34,228 @@ impl Shooter {
         self.shooted = 0;
         self.finished = false;
     }
-}
\ No newline at end of file
+}
+/// Fixed delta in seconds every frame advances by. `None` follows the wall
+/// clock, the headless app sets it so runs are reproducible.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimulationStep(pub Option<f32>);
+impl SimulationStep {
+    pub fn delta(&self, time: &Time) -> Duration {
+        match self.0 {
+            Some(step) => Duration::from_secs_f32(step),
+            None => time.delta(),
+        }
+    }
+}
+
+/// Time the game advances this frame, see `SimulationStep`.
+#[derive(SystemParam)]
+pub struct SimulationClock<'a> {
+    pub time: Res<'a, Time>,
+    pub step: Res<'a, SimulationStep>,
+    pub game_speed: Res<'a, GameSpeed>,
+}
+impl<'a> SimulationClock<'a> {
+    pub fn delta(&self) -> Duration {
+        self.step.delta(&self.time)
+    }
+
+    /// `delta` sped up while fast forwarding.
+    pub fn game_delta(&self) -> Duration {
+        self.delta().mul_f32(self.game_speed.0)
+    }
+}
+
+/// Progress of the current game, reset for a new game and restored from a
+/// saved one.
+#[derive(SystemParam)]
+pub struct GameProgress<'a> {
+    pub game_rng: ResMut<'a, GameRng>,
+    pub shooter: ResMut<'a, Shooter>,
+    pub scoreboard: ResMut<'a, Scoreboard>,
+    pub launch_point: ResMut<'a, LaunchPoint>,
+}
+
+/// Paces the blocks sliding down a row in `GameState::MovingBlocks`.
+pub struct BlockDescent(pub Timer);
+impl Default for BlockDescent {
+    fn default() -> Self {
+        BlockDescent(Timer::from_seconds(CONFIG.block_descent_time, false))
+    }
+}
+
+/// Paces ball launches while shooting.
+pub struct BallSpawnTimer(pub Timer);
+impl Default for BallSpawnTimer {
+    fn default() -> Self {
+        BallSpawnTimer(Timer::from_seconds(0.1, true))
+    }
+}
+
+/// Source of randomness for everything happening during a game. ChaCha can
+/// report and restore its position, which saved games rely on.
+pub struct GameRng(pub ChaCha8Rng);
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(ChaCha8Rng::from_entropy())
+    }
+}
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+/// Seed of the board of the next game. Unless it is fixed by the config or the
+/// command line a new one is rolled after every game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelSeed {
+    pub seed: u64,
+    pub fixed: bool,
+}
+impl Default for LevelSeed {
+    fn default() -> Self {
+        match CONFIG.seed {
+            Some(seed) => LevelSeed::fixed(seed),
+            None => LevelSeed::random(),
+        }
+    }
+}
+impl LevelSeed {
+    pub fn fixed(seed: u64) -> Self {
+        LevelSeed { seed, fixed: true }
+    }
+    pub fn random() -> Self {
+        LevelSeed { seed: rand::random(), fixed: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GameMode {
+    /// Clear the board to win.
+    Classic,
+    /// A new row comes in every turn until the blocks reach the bottom.
+    Endless,
+}
+impl Default for GameMode {
+    fn default() -> Self {
+        CONFIG.mode
+    }
+}
+
+/// Where balls are launched from. The first ball back on the floor decides
+/// where the next turn starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LaunchPoint {
+    pub x: f32,
+    pub next: Option<f32>,
+}
+impl LaunchPoint {
+    /// Launch point in the middle of the floor.
+    pub fn centered(playfield: &Playfield) -> Self {
+        LaunchPoint {
+            x: playfield.center().x,
+            next: None,
+        }
+    }
+
+    pub fn position(&self, playfield: &Playfield) -> Vec2 {
+        Vec2::new(self.x, playfield.min().y)
+    }
+}
+
+/// Board geometry in world units: a grid of `columns` by `rows` cells of
+/// `cell_size`, with its bottom left corner at `origin`. Walls enclose
+/// exactly this area and the camera is scaled to show all of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playfield {
+    pub columns: usize,
+    pub rows: usize,
+    pub cell_size: f32,
+    pub origin: Vec2,
+}
+impl Default for Playfield {
+    fn default() -> Self {
+        Playfield::centered(CONFIG.columns, CONFIG.rows, CONFIG.block_size)
+    }
+}
+impl Playfield {
+    /// Rows below the field, left free for aiming.
+    pub const FIELD_OFFSET: usize = 5;
+
+    /// Playfield centered on the world origin.
+    pub fn centered(columns: usize, rows: usize, cell_size: f32) -> Self {
+        let size = Vec2::new(columns as f32, rows as f32) * cell_size;
+        Playfield {
+            columns,
+            rows,
+            cell_size,
+            origin: -size / 2.,
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.columns as f32, self.rows as f32) * self.cell_size
+    }
+
+    /// Bottom left corner.
+    pub fn min(&self) -> Vec2 {
+        self.origin
+    }
+
+    /// Top right corner.
+    pub fn max(&self) -> Vec2 {
+        self.origin + self.size()
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.origin + self.size() / 2.
+    }
+
+    /// Highest field row that is still inside the playfield, 0 for fields
+    /// too short to have any row above the offset.
+    pub fn top_row(&self) -> usize {
+        self.rows.saturating_sub(Self::FIELD_OFFSET)
+    }
+
+    /// Center of the block at `field_pos`, also for blocks that moved below
+    /// the first row.
+    pub fn cell_center(&self, field_pos: (i32, i32)) -> Vec2 {
+        let x = field_pos.0 as f32 + 0.5;
+        let y = field_pos.1 as f32 - 0.5 + Self::FIELD_OFFSET as f32;
+        self.origin + Vec2::new(x, y) * self.cell_size
+    }
+
+    /// Field position of the cell containing `position`.
+    pub fn cell_at(&self, position: Vec2) -> (i32, i32) {
+        let cell = (position - self.origin) / self.cell_size;
+        let x = cell.x - 0.5;
+        let y = cell.y + 0.5 - Self::FIELD_OFFSET as f32;
+        (x.round() as i32, y.round() as i32)
+    }
+
+    /// World units per window pixel so the whole playfield fits a window of
+    /// `window` pixels.
+    pub fn view_scale(&self, window: Vec2) -> f32 {
+        let size = self.size();
+        (size.x / window.x).max(size.y / window.y)
+    }
+
+    /// World position under `cursor`, given in window pixels from the bottom
+    /// left corner.
+    pub fn window_to_world(&self, cursor: Vec2, window: Vec2) -> Vec2 {
+        self.center() + (cursor - window / 2.) * self.view_scale(window)
+    }
+}
+
+/// How much faster than real time a round runs, raised while fast forwarding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameSpeed(pub f32);
+impl Default for GameSpeed {
+    fn default() -> Self {
+        GameSpeed(1.)
+    }
+}
+impl GameSpeed {
+    pub const FAST_FORWARD: f32 = 3.;
+}
@@ -0,0 +1,251 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use ron::de::from_reader;
+use ron::ser::{PrettyConfig, to_string_pretty};
+use serde::{Deserialize, Serialize};
+
+use crate::GameState;
+use crate::builder::BlockSpawner;
+use crate::entity::{Block, ContinueButton, GridPos, Scoreboard};
+use crate::highscore::{Board, LastBoard, PendingHighScore, PlayedBoard};
+use crate::level::SelectedLevel;
+use crate::resource::{GameMode, GameProgress, GameRng, HasWon, LaunchPoint, LevelSeed, Shooter};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedBlock {
+    pub position: (i32, i32),
+    pub block: Block,
+}
+
+/// A game in progress, written at the start of every aiming phase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub state: GameState,
+    pub mode: GameMode,
+    pub seed: u64,
+    /// Level file the board came from, `None` for a generated map.
+    #[serde(default)]
+    pub level: Option<String>,
+    /// Words drawn from the rng seeded with `seed`.
+    pub rng_word_pos: u64,
+    pub balls: u32,
+    pub score: usize,
+    pub turns: u32,
+    /// Where the next turn's balls start.
+    #[serde(default)]
+    pub launch_x: f32,
+    pub blocks: Vec<SavedBlock>,
+}
+
+/// Where the running game is saved, nothing is written without a path.
+#[derive(Debug, Default)]
+pub struct SaveSlot {
+    pub path: Option<PathBuf>,
+}
+
+impl SaveSlot {
+    /// `save.ron` in the platform data directory, next to the high scores.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("brickgame").join("save.ron"))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.as_ref().map_or(false, |path| path.exists())
+    }
+
+    pub fn load(&self) -> Option<SaveGame> {
+        let file = File::open(self.path.as_ref()?).ok()?;
+        match from_reader(file) {
+            Ok(game) => Some(game),
+            Err(e) => {
+                warn!("ignoring unreadable save: {}", e);
+                None
+            },
+        }
+    }
+
+    pub fn write(&self, game: &SaveGame) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let ron = to_string_pretty(game, PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        fs::write(path, ron)
+    }
+
+    pub fn clear(&self) {
+        if let Some(path) = &self.path {
+            if path.exists() {
+                if let Err(e) = fs::remove_file(path) {
+                    warn!("could not remove save: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Mode, seed and level the session was started with, put aside while a
+/// continued game plays with its own.
+#[derive(Clone)]
+pub struct SessionSettings {
+    pub mode: GameMode,
+    pub level_seed: LevelSeed,
+    pub selected_level: SelectedLevel,
+}
+
+/// Settings deciding the board of the next game.
+#[derive(SystemParam)]
+pub struct GameSettings<'a> {
+    pub game_mode: ResMut<'a, GameMode>,
+    pub level_seed: ResMut<'a, LevelSeed>,
+    pub selected_level: ResMut<'a, SelectedLevel>,
+    pub session: ResMut<'a, Option<SessionSettings>>,
+    pub asset_server: Res<'a, AssetServer>,
+}
+impl<'a> GameSettings<'a> {
+    /// Switches to the settings `game` was played with, the session's are
+    /// kept until it is over.
+    fn continue_game(&mut self, game: &SaveGame) {
+        if self.session.is_none() {
+            *self.session = Some(SessionSettings {
+                mode: *self.game_mode,
+                level_seed: *self.level_seed,
+                selected_level: self.selected_level.clone(),
+            });
+        }
+        *self.game_mode = game.mode;
+        self.level_seed.seed = game.seed;
+        if game.level != self.selected_level.path {
+            *self.selected_level = SelectedLevel {
+                path: game.level.clone(),
+                handle: game.level.as_ref().map(|path| self.asset_server.load(path.as_str())),
+            };
+        }
+    }
+}
+
+/// Set when a turn starts. The save is written in `PostUpdate`, once the
+/// blocks spawned this frame exist.
+#[derive(Debug, Default)]
+pub struct AutosaveRequested(pub bool);
+
+pub fn request_autosave_system(mut requested: ResMut<AutosaveRequested>) {
+    requested.0 = true;
+}
+
+pub fn autosave_system(
+    mut requested: ResMut<AutosaveRequested>,
+    save_slot: Res<SaveSlot>,
+    game_state: Res<State<GameState>>,
+    played: PlayedBoard,
+    progress: GameProgress,
+    block_query: Query<(&GridPos, &Block)>,
+) {
+    if !requested.0 {
+        return;
+    }
+    requested.0 = false;
+    let LastBoard { mode, board } = played.current();
+    let game = SaveGame {
+        state: game_state.current().clone(),
+        mode,
+        seed: played.level_seed.seed,
+        level: match board {
+            Board::Level(path) => Some(path),
+            Board::Seed(_) => None,
+        },
+        // a game never draws anywhere near 2^64 words
+        rng_word_pos: progress.game_rng.0.get_word_pos() as u64,
+        balls: progress.shooter.count,
+        score: progress.scoreboard.score,
+        turns: progress.scoreboard.turns,
+        launch_x: progress.launch_point.x,
+        blocks: block_query
+            .iter()
+            .map(|(grid_pos, block)| SavedBlock {
+                position: (grid_pos.0, grid_pos.1),
+                block: block.clone(),
+            })
+            .collect(),
+    };
+    if let Err(e) = save_slot.write(&game) {
+        warn!("could not save the game: {}", e);
+    }
+}
+
+/// A finished game can not be continued.
+pub fn clear_finished_save_system(
+    has_won: Res<HasWon>,
+    save_slot: Res<SaveSlot>,
+) {
+    if has_won.is_some() {
+        save_slot.clear();
+    }
+}
+
+pub fn continue_system(
+    keys: Res<Input<KeyCode>>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ContinueButton>)>,
+    save_slot: Res<SaveSlot>,
+    pending_high_score: Res<Option<PendingHighScore>>,
+    mut restore: ResMut<Option<SaveGame>>,
+    mut has_won: ResMut<HasWon>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let clicked = interaction_query.iter().any(|interaction| *interaction == Interaction::Clicked);
+    // while a name is typed `c` is just a letter
+    let pressed = pending_high_score.is_none() && keys.just_pressed(KeyCode::C);
+    if !clicked && !pressed {
+        return;
+    }
+    if let Some(game) = save_slot.load() {
+        let _ = game_state.set(game.state.clone());
+        *has_won = None;
+        *restore = Some(game);
+    }
+}
+
+/// Rebuilds the saved game when leaving `Init`, instead of `block_setup`.
+pub fn restore_game_system(
+    mut spawner: BlockSpawner,
+    mut progress: GameProgress,
+    mut restore: ResMut<Option<SaveGame>>,
+    mut settings: GameSettings,
+) {
+    let game = match restore.take() {
+        Some(game) => game,
+        None => return,
+    };
+    settings.continue_game(&game);
+    *progress.game_rng = GameRng::from_seed(game.seed);
+    progress.game_rng.0.set_word_pos(game.rng_word_pos as u128);
+    *progress.shooter = Shooter { count: game.balls, ..Shooter::default() };
+    *progress.scoreboard = Scoreboard { score: game.score, turns: game.turns };
+    *progress.launch_point = LaunchPoint { x: game.launch_x, next: None };
+    for saved in game.blocks {
+        spawner.block(saved.position, saved.block);
+    }
+}
+
+/// Puts the session's settings back once a continued game is over. Has to
+/// run after its high score was recorded.
+pub fn end_continued_game_system(
+    mut game_mode: ResMut<GameMode>,
+    mut level_seed: ResMut<LevelSeed>,
+    mut selected_level: ResMut<SelectedLevel>,
+    mut session: ResMut<Option<SessionSettings>>,
+) {
+    if let Some(session) = session.take() {
+        *game_mode = session.mode;
+        *level_seed = session.level_seed;
+        *selected_level = session.selected_level;
+    }
+}
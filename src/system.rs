@@ -1,45 +1,139 @@
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use heron::{CollisionEvent, Velocity};
+use bevy::utils::HashSet;
+use heron::{CollisionEvent, CollisionLayers, PhysicsTime, Velocity};
+use rand::Rng;
 
-use crate::{GameState, MousePos, Shooter, components::CollisionLayer, constants::CONFIG, entity::{Ball, Block}, resource::HasWon};
+use crate::blocktype::{Axis, BlockPalette, BlockRegistry, Corner, DestroyEffect, HitEffect, STANDARD, hit_from_above};
+use crate::{GameState, MousePos, Shooter, highscore::PendingHighScore, builder::{BallSpawner, BlockSpawner}, components::CollisionLayer, constants::CONFIG, entity::{Ball, BallAge, Block, Burst, ContinueButton, Floor, GridPos, FastForwardButton, HitPulse, Hud, LaunchMarker, RecallButton, Recalled, Scoreboard, Shard, ShootingControls, SplitBall}, level::{Level, SelectedLevel}, gameassets::GameAssets, levelgen::row_gen, resource::{BlockDescent, GameMode, GameRng, GameSpeed, HasWon, LaunchPoint, Playfield, SimulationClock, SimulationStep}};
+use bevy::render::camera::{Camera, CameraProjection, OrthographicProjection};
+use bevy::render::render_graph::base::camera::CAMERA_2D;
+use bevy::window::{WindowCreated, WindowResized};
 
-pub fn collision_events(
-    mut commands: Commands,
+/// A ball started touching a block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub ball: Entity,
+    pub block: Entity,
+}
+
+/// Where `Contact`s come from. Tests switch to `External` and send their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactSource {
+    Physics,
+    External,
+}
+impl Default for ContactSource {
+    fn default() -> Self {
+        ContactSource::Physics
+    }
+}
+
+/// Turns physics collisions between balls and blocks into `Contact`s as soon
+/// as they start.
+pub fn physics_contact_system(
+    source: Res<ContactSource>,
     mut collision_events: EventReader<CollisionEvent>,
-    mut game_events: EventWriter<GameEvents>,
-    mut block_query: Query<(Entity, &mut Block)>,
+    mut contacts: EventWriter<Contact>,
 ) {
-    collision_events
-    .iter()
-    // We care about when the entities "stopp" to collide
-    .filter(|e| e.is_stopped())
-    .filter_map(|event| {
+    for event in collision_events.iter() {
+        // drain the reader either way, stale events must not leak in later
+        if *source != ContactSource::Physics || !event.is_started() {
+            continue;
+        }
         let (entity_1, entity_2) = event.rigid_body_entities();
-        
         let (layers_1, layers_2) = event.collision_layers();
-
-        if !layers_1.contains_group(CollisionLayer::Ball) {
-            Some(entity_1)
-        } else if !layers_2.contains_group(CollisionLayer::Ball) {
-            Some(entity_2)
-        } else {
-            None
+        let is_block = |layers: CollisionLayers| {
+            layers.contains_group(CollisionLayer::Block) || layers.contains_group(CollisionLayer::BlockSensor)
+        };
+        if layers_1.contains_group(CollisionLayer::Ball) && is_block(layers_2) {
+            contacts.send(Contact { ball: entity_1, block: entity_2 });
+        } else if layers_2.contains_group(CollisionLayer::Ball) && is_block(layers_1) {
+            contacts.send(Contact { ball: entity_2, block: entity_1 });
         }
+    }
+}
+
+/// Contacts coming in and the events block hits send out.
+#[derive(SystemParam)]
+pub struct ContactEvents<'a> {
+    pub contacts: EventReader<'a, Contact>,
+    pub game_events: EventWriter<'a, GameEvents>,
+    pub lasers: EventWriter<'a, Laser>,
+}
+
+pub fn collision_events(
+    mut balls: BallSpawner,
+    mut events: ContactEvents,
+    mut block_query: Query<(&mut Block, &GridPos, &Transform)>,
+    mut ball_query: Query<(&Transform, &mut Velocity), With<Ball>>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut game_rng: ResMut<GameRng>,
+    registry: Res<BlockRegistry>,
+) {
+    // A ball bounces off one solid block per frame, touching two at once is
+    // a single hit on the first. Sensors it passes count once each.
+    let mut hits: HashSet<(Entity, Option<Entity>)> = HashSet::default();
+    let contacts: Vec<(Entity, Entity)> = events
+    .contacts
+    .iter()
+    .map(|contact| (contact.ball, contact.block))
+    .filter(|(ball_entity, block_entity)| {
+        let solid = block_query
+            .get_mut(*block_entity)
+            .map_or(false, |(block, _, _)| registry.is_solid(&block.kind));
+        hits.insert((*ball_entity, if solid { None } else { Some(*block_entity) }))
     })
-    .for_each(|block_entity| {
-        let may_block = block_query.get_mut(block_entity);
-        if let Ok(mut block) = may_block {
-            match *block.1 {
-                Block::Standard(health) => {
-                    if health > 0 {
-                        *block.1 = Block::Standard(health - 1);
-                    }
-                },
-                Block::AddBall => {
-                    game_events.send(GameEvents::AddBall);
-                    commands.entity(block_entity).despawn_recursive();
-                },
+    .collect();
+    contacts
+    .into_iter()
+    .for_each(|(ball_entity, block_entity)| {
+        if let Ok((mut block, grid_pos, block_transform)) = block_query.get_mut(block_entity) {
+            // already destroyed by an earlier hit
+            if block.health == 0 {
+                return;
+            }
+            let def = match registry.get(&block.kind) {
+                Some(def) => def,
+                None => return,
+            };
+            for effect in &def.on_hit {
+                match effect {
+                    HitEffect::Damage => {
+                        block.health -= 1;
+                        scoreboard.score += Scoreboard::HIT_POINTS;
+                    },
+                    HitEffect::DamageFromAbove => {
+                        let from_above = ball_query.get_mut(ball_entity).map_or(false, |(transform, _)| {
+                            hit_from_above(transform.translation.truncate(), block_transform.translation.truncate())
+                        });
+                        if from_above {
+                            block.health -= 1;
+                            scoreboard.score += Scoreboard::HIT_POINTS;
+                        }
+                    },
+                    HitEffect::AddBall => events.game_events.send(GameEvents::AddBall),
+                    HitEffect::Consume => block.health = 0,
+                    HitEffect::Laser(axis) => events.lasers.send(Laser { origin: *grid_pos, axis: *axis }),
+                    HitEffect::Split => {
+                        if let Ok((transform, velocity)) = ball_query.get_mut(ball_entity) {
+                            let mirrored = Vec2::new(-velocity.linear.x, velocity.linear.y);
+                            let ball = balls.ball(transform.translation.truncate(), mirrored);
+                            balls.commands.entity(ball).insert(SplitBall);
+                        }
+                    },
+                    HitEffect::Deflect => {
+                        if let Ok((_, mut velocity)) = ball_query.get_mut(ball_entity) {
+                            let angle = game_rng.0.gen_range(0.0..std::f32::consts::TAU);
+                            let speed = velocity.linear.length();
+                            velocity.linear = Vec3::new(angle.cos(), angle.sin(), 0.) * speed;
+                        }
+                    },
+                }
+                if block.health == 0 {
+                    break;
+                }
             }
         }
         
@@ -48,10 +142,6 @@ pub fn collision_events(
 pub fn read_game_events(
     mut game_events: EventReader<GameEvents>,
     mut shooter_count: ResMut<Shooter>,
-    ball_query: Query<&Ball>,
-    mut game_state: ResMut<State<GameState>>,
-    
-
 ){
     for game_event in game_events.iter() {
         match *game_event {
@@ -59,14 +149,57 @@ pub fn read_game_events(
                 shooter_count.count += 1;
 
             },
-            GameEvents::DestroyBall => {
-                if *game_state.current() == GameState::Shooting {
-                    if ball_query.iter().len() <= 1 {
-                        shooter_count.finished = false;
-                        shooter_count.shooted = 0;
-                        let _ = game_state.set(GameState::MovingBlocks);
-                    }
-                }
+            // handled by `end_round_system`
+            GameEvents::DestroyBall => {},
+        }
+    }
+}
+
+/// Ends the round on a `DestroyBall` once every ball was launched and none is
+/// left. Retired balls are despawned through commands, which only apply at
+/// the end of the stage, so this reads the events first thing in the
+/// following frame: before the systems retiring balls, and before
+/// `ball_setup` so the last launched ball already exists.
+pub fn end_round_system(
+    mut game_events: EventReader<GameEvents>,
+    mut shooter: ResMut<Shooter>,
+    ball_query: Query<(), With<Ball>>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let retired = game_events.iter().any(|event| *event == GameEvents::DestroyBall);
+    if retired && shooter.finished && ball_query.iter().next().is_none() {
+        shooter.reset();
+        let _ = game_state.set(GameState::MovingBlocks);
+    }
+}
+
+/// Block materials and the palette they are shaded from.
+#[derive(SystemParam)]
+pub struct BlockShading<'a> {
+    pub shade_query: Query<'a, (&'static Block, &'static mut Handle<ColorMaterial>)>,
+    pub palette: ResMut<'a, BlockPalette>,
+    pub materials: ResMut<'a, Assets<ColorMaterial>>,
+}
+impl<'a> BlockShading<'a> {
+    /// Shades are relative to the strongest block, so any change can recolor
+    /// all of them.
+    fn recolor(&mut self, registry: &BlockRegistry) {
+        self.palette.max_health = self
+            .shade_query
+            .iter_mut()
+            .filter(|(block, _)| registry.types.get(&block.kind).map_or(false, |def| def.weak_color.is_some()))
+            .map(|(block, _)| block.health)
+            .max()
+            .unwrap_or(0);
+        for (block, mut material) in self.shade_query.iter_mut() {
+            let def = match registry.types.get(&block.kind) {
+                Some(def) if def.weak_color.is_some() && block.health >= 1 => def,
+                _ => continue,
+            };
+            let texture = self.materials.get(&*material).and_then(|material| material.texture.clone());
+            let shaded = self.palette.material(&block.kind, def, block.health, texture, &mut self.materials);
+            if *material != shaded {
+                *material = shaded;
             }
         }
     }
@@ -74,43 +207,289 @@ pub fn read_game_events(
 
 pub fn update_block_text(
     mut commands: Commands,
-    block_query: Query<(Entity, &Children, &Block), Changed<Block>>,
+    block_query: Query<(Entity, &Children, &Block, ChangeTrackers<Block>, Option<&HitPulse>, &GridPos, &Transform, &Sprite), Changed<Block>>,
+    mut shading: BlockShading,
     mut collider_text_query: Query<&mut Text>,
-
+    mut scoreboard: ResMut<Scoreboard>,
+    mut explosions: EventWriter<Explosion>,
+    registry: Res<BlockRegistry>,
 ){
-    for (entity, children,block) in block_query.iter() {
-        if let &Block::Standard(health) = block {
-            if health >= 1 {
+    let mut changed = false;
+    for (entity, children, block, tracker, pulse, grid_pos, transform, sprite) in block_query.iter() {
+        changed = true;
+        let def = match registry.get(&block.kind) {
+            Some(def) => def,
+            None => continue,
+        };
+        let material = match shading.shade_query.get_mut(entity) {
+            Ok((_, material)) => material.clone(),
+            Err(_) => continue,
+        };
+        if block.health >= 1 {
+            if def.label.is_none() {
                 if let Ok(mut child) = collider_text_query.get_mut(children[0]) {
-                    child.sections[0].value = health.to_string();
+                    child.sections[0].value = block.health.to_string();
                 }
-            } else {
-                commands.entity(entity).despawn_recursive();
             }
+            // freshly spawned blocks were not hit
+            if tracker.is_added() {
+                continue;
+            }
+            let flash = match pulse {
+                Some(pulse) => pulse.flash,
+                None => {
+                    let texture = shading.materials.get(&material).and_then(|material| material.texture.clone());
+                    let flash = commands
+                        .spawn_bundle(SpriteBundle {
+                            material: shading.palette.flash(texture, &mut shading.materials),
+                            transform: Transform::from_xyz(0., 0., 0.05),
+                            sprite: Sprite::new(sprite.size),
+                            visible: Visible { is_visible: true, is_transparent: true },
+                            ..Default::default()
+                        })
+                        .id();
+                    commands.entity(entity).push_children(&[flash]);
+                    flash
+                },
+            };
+            commands.entity(entity).insert(HitPulse { age: 0., flash });
+        } else {
+            for effect in &def.on_destroy {
+                match effect {
+                    DestroyEffect::Score => scoreboard.score += Scoreboard::DESTROY_POINTS,
+                    DestroyEffect::Explode { radius, damage } => explosions.send(Explosion {
+                        center: *grid_pos,
+                        radius: *radius,
+                        damage: *damage,
+                    }),
+                }
+            }
+            spawn_shards(&mut commands, material, transform, sprite.size);
+            commands.entity(entity).despawn_recursive();
         }
     }
+    if changed {
+        shading.recolor(&registry);
+    }
 }
-pub fn ball_wall_collision_system(
+
+/// Breaks a destroyed block into four quarters flying apart.
+fn spawn_shards(commands: &mut Commands, material: Handle<ColorMaterial>, transform: &Transform, size: Vec2) {
+    for corner in Corner::ALL.iter() {
+        let direction = corner.direction();
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                transform: Transform::from_translation(transform.translation + (direction * size / 4.).extend(0.)),
+                sprite: Sprite::new(size / 2.),
+                ..Default::default()
+            })
+            .insert(Shard {
+                age: 0.,
+                velocity: direction * size * 2.,
+                spin: direction.x * 6.,
+            });
+    }
+}
+
+/// Swells hit blocks and takes the flash away once the pulse is over.
+pub fn hit_pulse_system(
     mut commands: Commands,
-    mut game_events: EventWriter<GameEvents>,
-    mut ball_query: Query<(Entity, &Transform, &mut Velocity), With<Ball>>,
+    mut pulse_query: Query<(Entity, &mut HitPulse, &mut Transform)>,
+    time: Res<Time>,
+    step: Res<SimulationStep>,
 ) {
-    for (ball_entity, ball_transform, mut velocity) in ball_query.iter_mut() {
-        // checking borders and flip if on wall or despawn on ground
-        if ball_transform.translation.y < -CONFIG.window_height / 2.{
+    let delta = step.delta(&time).as_secs_f32();
+    for (entity, mut pulse, mut transform) in pulse_query.iter_mut() {
+        pulse.age += delta;
+        if pulse.age >= HitPulse::DURATION {
+            transform.scale = Vec3::ONE;
+            // also drops the flash from the block's `Children`
+            commands.entity(pulse.flash).despawn_recursive();
+            commands.entity(entity).remove::<HitPulse>();
+        } else {
+            let swell = (pulse.age / HitPulse::DURATION * std::f32::consts::PI).sin();
+            transform.scale = Vec3::splat(1. + 0.15 * swell);
+        }
+    }
+}
 
-            game_events.send(GameEvents::DestroyBall);
-            commands.entity(ball_entity).despawn();
-            continue;
+pub fn shard_system(
+    mut commands: Commands,
+    mut shard_query: Query<(Entity, &mut Shard, &mut Transform)>,
+    time: Res<Time>,
+    step: Res<SimulationStep>,
+) {
+    let delta = step.delta(&time).as_secs_f32();
+    for (entity, mut shard, mut transform) in shard_query.iter_mut() {
+        shard.age += delta;
+        if shard.age >= Shard::DURATION {
+            commands.entity(entity).despawn();
+        } else {
+            transform.translation += (shard.velocity * delta).extend(0.);
+            transform.rotate(Quat::from_rotation_z(shard.spin * delta));
+            transform.scale = Vec3::splat(1. - shard.age / Shard::DURATION);
         }
-        if ball_transform.translation.y > CONFIG.window_height / 2. && velocity.linear.y > 0. {
-            velocity.linear *= Vec3::new(1.,-1.,1.); 
+    }
+}
+
+/// A bomb went off at `center`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Explosion {
+    pub center: GridPos,
+    pub radius: i32,
+    pub damage: u32,
+}
+
+/// Damages the blocks around exploded bombs. Bombs destroyed this way go off
+/// through `update_block_text` in turn.
+pub fn explosion_system(
+    mut commands: Commands,
+    mut explosions: EventReader<Explosion>,
+    assets: Res<GameAssets>,
+    mut block_query: Query<(&mut Block, &GridPos)>,
+    mut scoreboard: ResMut<Scoreboard>,
+    registry: Res<BlockRegistry>,
+    playfield: Res<Playfield>,
+) {
+    for explosion in explosions.iter() {
+        let GridPos(x, y) = explosion.center;
+        for (mut block, grid_pos) in block_query.iter_mut() {
+            let in_range = (grid_pos.0 - x).abs() <= explosion.radius && (grid_pos.1 - y).abs() <= explosion.radius;
+            // destroyed blocks, including the bomb itself, are left alone
+            if !in_range || block.health == 0 || !registry.is_damageable(&block.kind) {
+                continue;
+            }
+            block.health = block.health.saturating_sub(explosion.damage);
+            scoreboard.score += Scoreboard::HIT_POINTS;
+        }
+
+        let size = (2 * explosion.radius + 1) as f32 * playfield.cell_size;
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: assets.explosion.clone(),
+                transform: Transform::from_translation(playfield.cell_center((x, y)).extend(3.)),
+                sprite: Sprite::new(Vec2::new(size, size)),
+                visible: Visible { is_visible: true, is_transparent: true },
+                ..Default::default()
+            })
+            .insert(Burst::default());
+    }
+}
+
+/// A ball passed a laser block at `origin`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Laser {
+    pub origin: GridPos,
+    pub axis: Axis,
+}
+
+/// Damages every standard block in the row or column of a fired laser.
+/// Armored and shaped blocks are left alone.
+pub fn laser_system(
+    mut commands: Commands,
+    mut lasers: EventReader<Laser>,
+    assets: Res<GameAssets>,
+    mut block_query: Query<(&mut Block, &GridPos)>,
+    mut scoreboard: ResMut<Scoreboard>,
+    playfield: Res<Playfield>,
+) {
+    for laser in lasers.iter() {
+        let GridPos(x, y) = laser.origin;
+        for (mut block, grid_pos) in block_query.iter_mut() {
+            let in_line = match laser.axis {
+                Axis::Row => grid_pos.1 == y,
+                Axis::Column => grid_pos.0 == x,
+            };
+            if !in_line || block.health == 0 || block.kind != STANDARD {
+                continue;
+            }
+            block.health -= 1;
+            scoreboard.score += Scoreboard::HIT_POINTS;
         }
-        if ball_transform.translation.x < -CONFIG.window_width / 2. && velocity.linear.x < 0. {
-            velocity.linear *= Vec3::new(-1.,1.,1.); 
+
+        let origin = playfield.cell_center((x, y));
+        let size = playfield.size();
+        let (center, beam) = match laser.axis {
+            Axis::Row => (Vec2::new(playfield.center().x, origin.y), Vec2::new(size.x, playfield.cell_size / 4.)),
+            Axis::Column => (Vec2::new(origin.x, playfield.center().y), Vec2::new(playfield.cell_size / 4., size.y)),
+        };
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: assets.laser_beam.clone(),
+                transform: Transform::from_translation(center.extend(3.)),
+                sprite: Sprite::new(beam),
+                visible: Visible { is_visible: true, is_transparent: true },
+                ..Default::default()
+            })
+            .insert(Burst::default());
+    }
+}
+
+/// Shrinks explosion bursts and laser beams away.
+pub fn burst_system(
+    mut commands: Commands,
+    mut burst_query: Query<(Entity, &mut Burst, &mut Transform)>,
+    time: Res<Time>,
+    step: Res<SimulationStep>,
+) {
+    let delta = step.delta(&time).as_secs_f32();
+    for (entity, mut burst, mut transform) in burst_query.iter_mut() {
+        burst.age += delta;
+        if burst.age >= Burst::DURATION {
+            commands.entity(entity).despawn();
+        } else {
+            transform.scale = Vec3::splat(1. - burst.age / Burst::DURATION);
         }
-        if ball_transform.translation.x > CONFIG.window_width / 2. && velocity.linear.x > 0. {
-            velocity.linear *= Vec3::new(-1.,1.,1.); 
+    }
+}
+
+/// Retires balls reaching the floor sensor. Balls that somehow got past the
+/// walls are retired as well.
+pub fn floor_collision_system(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut game_events: EventWriter<GameEvents>,
+    ball_query: Query<(Entity, &Transform, Option<&SplitBall>), With<Ball>>,
+    floor_query: Query<(), With<Floor>>,
+    mut launch_point: ResMut<LaunchPoint>,
+    playfield: Res<Playfield>,
+) {
+    let mut landed: Vec<Entity> = collision_events
+        .iter()
+        .filter(|e| e.is_started())
+        .filter_map(|event| {
+            let (entity_1, entity_2) = event.rigid_body_entities();
+            if floor_query.get(entity_1).is_ok() {
+                Some(entity_2)
+            } else if floor_query.get(entity_2).is_ok() {
+                Some(entity_1)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let margin = Vec2::splat(playfield.cell_size);
+    let (min, max) = (playfield.min() - margin, playfield.max() + margin);
+    landed.extend(ball_query.iter()
+        .filter(|(_, transform, _)| {
+            let position = transform.translation.truncate();
+            position.x < min.x || position.y < min.y || position.x > max.x || position.y > max.y
+        })
+        .map(|(entity, _, _)| entity));
+    landed.sort();
+    landed.dedup();
+
+    for ball_entity in landed {
+        if let Ok((_, ball_transform, split)) = ball_query.get(ball_entity) {
+            if launch_point.next.is_none() && split.is_none() {
+                let left = playfield.min().x + CONFIG.ball_size;
+                let right = playfield.max().x - CONFIG.ball_size;
+                launch_point.next = Some(ball_transform.translation.x.max(left).min(right));
+            }
+
+            game_events.send(GameEvents::DestroyBall);
+            commands.entity(ball_entity).despawn();
         }
     }
 }
@@ -124,29 +503,116 @@ pub fn check_blocks_system(
     block_query: Query<&Block>,
     mut game_state: ResMut<State<GameState>>,
     mut has_won: ResMut<HasWon>,
+    game_mode: Res<GameMode>,
+    registry: Res<BlockRegistry>,
 ) {
+    // endless games can only be lost
+    if *game_mode == GameMode::Endless {
+        return;
+    }
     if *game_state.current() == GameState::Shooting {
-        if block_query.iter().len() == 0 {
+        if !block_query.iter().any(|block| registry.is_required(&block.kind)) {
             *has_won = Some(true);
             let _ = game_state.set(GameState::Init);
         }
     }
 }
+/// Moves every block a row down, `descend_blocks_system` slides them into
+/// their new cells.
 pub fn move_blocks_system(
-    mut collider_query: Query<&mut Transform, With<Block>>,
-    mut game_state: ResMut<State<GameState>>,
-    mut has_won: ResMut<HasWon>,
+    mut block_query: Query<&mut GridPos, With<Block>>,
+    mut descent: ResMut<BlockDescent>,
+) {
+    for mut grid_pos in block_query.iter_mut() {
+        grid_pos.1 -= 1;
+    }
+    descent.0.reset();
+}
+
+/// Where the game goes once the blocks have landed.
+#[derive(SystemParam)]
+pub struct TurnEnd<'a> {
+    pub game_state: ResMut<'a, State<GameState>>,
+    pub has_won: ResMut<'a, HasWon>,
+    pub scoreboard: ResMut<'a, Scoreboard>,
+}
+impl<'a> TurnEnd<'a> {
+    pub fn lose(&mut self) {
+        *self.has_won = Some(false);
+        let _ = self.game_state.set(GameState::Init);
+    }
+
+    pub fn next_turn(&mut self) {
+        self.scoreboard.turns += 1;
+        let _ = self.game_state.set(GameState::Aiming);
+    }
+}
+
+/// Slides the blocks towards their cells and ends the turn once they are
+/// there, lost if a block ended up on the loss line. Returns `true` only on
+/// the frame the descent finished with nothing across the line, `false` while
+/// the blocks are still moving and on a loss.
+pub fn descend_blocks_system(
+    mut commands: Commands,
+    mut block_query: Query<(Entity, &mut Transform, &GridPos, &Block)>,
+    mut descent: ResMut<BlockDescent>,
+    clock: SimulationClock,
+    mut turn_end: TurnEnd,
+    playfield: Res<Playfield>,
+    registry: Res<BlockRegistry>,
+) -> bool {
+    descent.0.tick(clock.delta());
+    let done = descent.0.finished();
+    // eased out, blocks settle into their cells
+    let progress = if done { 1. } else { 1. - (1. - descent.0.percent()).powi(2) };
+    for (_, mut transform, grid_pos, _) in block_query.iter_mut() {
+        let target = playfield.cell_center((grid_pos.0, grid_pos.1));
+        transform.translation.y = target.y + playfield.cell_size * (1. - progress);
+    }
+    if !done {
+        return false;
+    }
+
+    for (entity, _, grid_pos, block) in block_query.iter_mut() {
+        if playfield.cell_center((grid_pos.0, grid_pos.1)).y <= playfield.min().y + playfield.cell_size {
+            if registry.is_permanent(&block.kind) {
+                // drops off the board
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+            turn_end.lose();
+            return false;
+        }
+    }
+    turn_end.next_turn();
+    true
+}
 
+/// Fills the freed top of the field in endless mode.
+pub fn spawn_rows_system(
+    In(survived): In<bool>,
+    mut spawner: BlockSpawner,
+    game_mode: Res<GameMode>,
+    mut game_rng: ResMut<GameRng>,
+    scoreboard: Res<Scoreboard>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
 ) {
-    for mut transform in collider_query.iter_mut() {
-        if transform.translation.y <= -CONFIG.window_height / 2. + 2. * CONFIG.block_size {
-            *has_won = Some(false);
-            let _ = game_state.set(GameState::Init);
-            return;
+    if !survived || *game_mode != GameMode::Endless {
+        return;
+    }
+    let rows = selected_level
+        .handle
+        .as_ref()
+        .and_then(|handle| levels.get(handle))
+        .map_or(1, |level| level.rows_per_turn);
+    // rows beyond the first queue up above the playfield
+    let top_row = spawner.playfield.top_row();
+    for row in top_row..top_row + rows {
+        for brick in row_gen(spawner.playfield.columns, row, scoreboard.turns + 1, &mut game_rng.0) {
+            spawner.brick(&brick);
         }
-        transform.translation += Vec3::new(0., -CONFIG.block_size, 0.);
     }
-    let _ = game_state.set(GameState::Aiming);
 }
 
 pub fn mouse_listener_system(
@@ -156,29 +622,39 @@ pub fn mouse_listener_system(
     windows: Res<Windows>,
     mut mouse_pos: ResMut<MousePos>,
     mut game_state: ResMut<State<GameState>>,
+    pending_high_score: Res<Option<PendingHighScore>>,
+    playfield: Res<Playfield>,
 ) {
     match *game_state.current() {
         GameState::Aiming => {
-            let window = windows.get_primary().unwrap();
-
             if btns.just_pressed(MouseButton::Left) {
                 // For multi-window applications, you need to use a specific window ID here.
-                if let Some(position) = window.cursor_position() {
-                    *mouse_pos = position;
+                // There is no window when running headless.
+                if let Some(window) = windows.get_primary() {
+                    if let Some(cursor) = window.cursor_position() {
+                        let size = Vec2::new(window.width(), window.height());
+                        *mouse_pos = playfield.window_to_world(cursor, size);
+                    }
                 }
                 game_state.set(GameState::Shooting).unwrap();
             }
         },
         GameState::Init => {
+            // the keyboard belongs to the name entry
+            if pending_high_score.is_some() {
+                return;
+            }
             if keys.pressed(KeyCode::Space) {
                 let _ = game_state.set(GameState::Aiming);
+            } else if keys.just_pressed(KeyCode::H) {
+                let _ = game_state.push(GameState::HighScores);
             }
         },
-        GameState::Shooting => {
-            if keys.pressed(KeyCode::A) {
-                let _ = game_state.set(GameState::MovingBlocks);
+        GameState::HighScores => {
+            if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(KeyCode::H) {
+                let _ = game_state.pop();
             }
-        }
+        },
         _ => {}
     }
 
@@ -187,7 +663,7 @@ pub fn mouse_listener_system(
 pub fn button_system(
     interaction_query: Query<
         &Interaction,
-        (Changed<Interaction>, With<Button>),
+        (Changed<Interaction>, With<Button>, Without<ContinueButton>),
     >,
     mut has_won: ResMut<HasWon>,
     mut game_state: ResMut<State<GameState>>,
@@ -210,6 +686,222 @@ pub fn despawn_button_system(
     .for_each(|e| commands.entity(e).despawn_recursive());
 }
 
+pub fn update_hud_system(
+    scoreboard: Res<Scoreboard>,
+    mut hud_query: Query<&mut Text, With<Hud>>,
+    new_hud_query: Query<(), Added<Hud>>,
+) {
+    if !scoreboard.is_changed() && new_hud_query.iter().next().is_none() {
+        return;
+    }
+    for mut text in hud_query.iter_mut() {
+        text.sections[0].value = format!("score {}   turn {}", scoreboard.score, scoreboard.turns + 1);
+    }
+}
+
+pub fn despawn_hud_system(
+    mut commands: Commands,
+    hud_query: Query<Entity, With<Hud>>,
+) {
+    hud_query
+    .iter()
+    .for_each(|e| commands.entity(e).despawn_recursive());
+}
+
+/// Scales the game camera so the whole playfield fits the window, whatever
+/// its size or aspect ratio. The rest of the window shows the clear color.
+pub fn camera_scale_system(
+    mut resized_events: EventReader<WindowResized>,
+    mut created_events: EventReader<WindowCreated>,
+    windows: Res<Windows>,
+    playfield: Res<Playfield>,
+    mut camera_query: Query<(&mut Camera, &mut OrthographicProjection, &mut Transform)>,
+    new_camera_query: Query<(), Added<Camera>>,
+) {
+    let window_changed = resized_events.iter().count() + created_events.iter().count() > 0;
+    if !window_changed && !playfield.is_changed() && new_camera_query.iter().next().is_none() {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let scale = playfield.view_scale(Vec2::new(window.width(), window.height()));
+    for (mut camera, mut projection, mut transform) in camera_query.iter_mut() {
+        if camera.name.as_deref() != Some(CAMERA_2D) {
+            continue;
+        }
+        projection.scale = scale;
+        camera.projection_matrix = projection.get_projection_matrix();
+        transform.translation = playfield.center().extend(transform.translation.z);
+    }
+}
+
+/// The next turn starts where the first ball of this one landed.
+pub fn advance_launch_point_system(mut launch_point: ResMut<LaunchPoint>) {
+    if let Some(x) = launch_point.next.take() {
+        launch_point.x = x;
+    }
+}
+
+pub fn update_launch_marker_system(
+    launch_point: Res<LaunchPoint>,
+    playfield: Res<Playfield>,
+    mut marker_query: Query<&mut Transform, With<LaunchMarker>>,
+    new_marker_query: Query<(), Added<LaunchMarker>>,
+) {
+    if !launch_point.is_changed() && new_marker_query.iter().next().is_none() {
+        return;
+    }
+    for mut transform in marker_query.iter_mut() {
+        transform.translation = launch_point.position(&playfield).extend(1.);
+    }
+}
+
+pub fn despawn_launch_marker_system(
+    mut commands: Commands,
+    marker_query: Query<Entity, With<LaunchMarker>>,
+) {
+    marker_query
+    .iter()
+    .for_each(|e| commands.entity(e).despawn_recursive());
+}
+
+/// Turns balls that bounce flat for too long downwards and retires balls that
+/// fly for too long, so every round ends.
+pub fn stuck_ball_system(
+    mut commands: Commands,
+    mut game_events: EventWriter<GameEvents>,
+    mut ball_query: Query<(Entity, &mut Velocity, &mut BallAge), Without<Recalled>>,
+    clock: SimulationClock,
+) {
+    let delta = clock.game_delta().as_secs_f32();
+    for (entity, mut velocity, mut age) in ball_query.iter_mut() {
+        age.lifetime += delta;
+        let speed = velocity.linear.truncate().length();
+        if age.lifetime > CONFIG.ball_max_lifetime || speed == 0. {
+            game_events.send(GameEvents::DestroyBall);
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if velocity.linear.y.abs() < CONFIG.stuck_vertical_speed {
+            age.flat += delta;
+        } else {
+            age.flat = 0.;
+        }
+        if age.flat > CONFIG.stuck_time {
+            let angle = CONFIG.stuck_nudge_angle.to_radians();
+            let side = if velocity.linear.x < 0. { -1. } else { 1. };
+            velocity.linear = Vec3::new(side * angle.cos(), -angle.sin(), 0.) * speed;
+            age.flat = 0.;
+        }
+    }
+}
+
+/// Collisions and nudges may change a ball's speed, put it back to
+/// `CONFIG.ballspeed`.
+pub fn constant_speed_system(
+    mut ball_query: Query<&mut Velocity, (With<Ball>, Without<Recalled>)>,
+) {
+    for mut velocity in ball_query.iter_mut() {
+        let speed = velocity.linear.length();
+        if speed > 0. && (speed - CONFIG.ballspeed).abs() > f32::EPSILON {
+            velocity.linear *= CONFIG.ballspeed / speed;
+        }
+    }
+}
+
+/// Speed recalled balls fly back to the launch point with.
+const RECALL_SPEED: f32 = 3000.;
+
+/// `R` or a click on the recall button.
+#[derive(SystemParam)]
+pub struct RecallInput<'a> {
+    pub keys: Res<'a, Input<KeyCode>>,
+    pub interaction_query: Query<'a, &'static Interaction, (Changed<Interaction>, With<RecallButton>)>,
+}
+impl<'a> RecallInput<'a> {
+    pub fn pressed(&self) -> bool {
+        self.keys.just_pressed(KeyCode::R)
+            || self.interaction_query.iter().any(|interaction| *interaction == Interaction::Clicked)
+    }
+}
+
+/// `R` or the recall button stops shooting and sends every ball straight back
+/// to the launch point, where it is retired like any other ball.
+pub fn recall_system(
+    mut commands: Commands,
+    input: RecallInput,
+    mut ball_query: Query<(Entity, &Transform, &mut Velocity, Option<&Recalled>), With<Ball>>,
+    mut shooter: ResMut<Shooter>,
+    launch_point: Res<LaunchPoint>,
+    playfield: Res<Playfield>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    if !input.pressed() {
+        return;
+    }
+    // no more launches, `end_round_system` ends the round once the balls are home
+    shooter.finished = true;
+    // nothing in flight would ever end the round
+    if ball_query.iter_mut().next().is_none() {
+        shooter.reset();
+        let _ = game_state.set(GameState::MovingBlocks);
+        return;
+    }
+    // just below the floor, so the balls are retired at the launch point
+    let home = (launch_point.position(&playfield) - Vec2::new(0., CONFIG.ball_size)).extend(0.);
+    for (entity, transform, mut velocity, recalled) in ball_query.iter_mut() {
+        if recalled.is_some() {
+            continue;
+        }
+        let offset = home - transform.translation;
+        if offset.length_squared() > 0. {
+            velocity.linear = offset.normalize() * RECALL_SPEED;
+        }
+        commands
+            .entity(entity)
+            .insert(Recalled)
+            // only the floor still catches recalled balls
+            .insert(CollisionLayers::none()
+                .with_group(CollisionLayer::Ball)
+                .with_mask(CollisionLayer::Floor));
+    }
+}
+
+/// Holding `F` or the fast forward button speeds up physics and shooting.
+pub fn fast_forward_system(
+    keys: Res<Input<KeyCode>>,
+    interaction_query: Query<&Interaction, With<FastForwardButton>>,
+    mut game_speed: ResMut<GameSpeed>,
+    mut physics_time: ResMut<PhysicsTime>,
+) {
+    let held = keys.pressed(KeyCode::F)
+        || interaction_query.iter().any(|interaction| *interaction == Interaction::Clicked);
+    let speed = if held { GameSpeed::FAST_FORWARD } else { 1. };
+    if game_speed.0 != speed {
+        game_speed.0 = speed;
+        physics_time.set_scale(speed);
+    }
+}
+
+pub fn reset_game_speed_system(
+    mut game_speed: ResMut<GameSpeed>,
+    mut physics_time: ResMut<PhysicsTime>,
+) {
+    *game_speed = GameSpeed::default();
+    physics_time.set_scale(game_speed.0);
+}
+
+pub fn despawn_shooting_controls_system(
+    mut commands: Commands,
+    controls_query: Query<Entity, With<ShootingControls>>,
+) {
+    controls_query
+    .iter()
+    .for_each(|e| commands.entity(e).despawn_recursive());
+}
+
 pub fn despawn_blocks_system(
     mut commands: Commands,
     block_query: Query<Entity, With<Block>>,
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use brickgame::aim::{DOT_SPACING, aim_dots, predict};
+use brickgame::constants::CONFIG;
+use brickgame::launch_direction;
+use brickgame::resource::Playfield;
+
+fn launch_point() -> Vec2 {
+    Vec2::new(0., Playfield::default().min().y)
+}
+
+#[test]
+fn path_bounces_off_the_side_wall() {
+    let prediction = predict(&Playfield::default(), launch_point(), Vec2::new(1., 0.2), &[]);
+
+    assert!(prediction.hit.is_none());
+    assert!(!prediction.points.is_empty());
+    let right = Playfield::default().max().x;
+    assert!(prediction.points.iter().all(|point| point.x <= right - CONFIG.ball_size + 10.));
+    let last = prediction.points.last().unwrap();
+    assert!(last.x < prediction.points.iter().map(|point| point.x).fold(f32::MIN, f32::max));
+}
+
+#[test]
+fn steep_path_bounces_off_the_top_wall() {
+    let playfield = Playfield::default();
+    let prediction = predict(&playfield, launch_point(), Vec2::new(0.2, 1.), &[]);
+
+    assert!(prediction.hit.is_none());
+    let heights: Vec<f32> = prediction.points.iter().map(|point| point.y).collect();
+    let peak = (0..heights.len()).max_by(|a, b| heights[*a].partial_cmp(&heights[*b]).unwrap()).unwrap();
+    // the ball's center turns around one radius below the top wall
+    let turn = playfield.max().y - CONFIG.ball_size;
+    assert!(heights[peak] > turn - DOT_SPACING && heights[peak] <= turn + 5.);
+    // and comes all the way back down to the floor
+    assert!(heights[peak..].windows(2).all(|pair| pair[1] < pair[0]));
+    assert!(*heights.last().unwrap() < launch_point().y + DOT_SPACING);
+}
+
+#[test]
+fn path_stops_at_the_first_block() {
+    let near = Vec2::new(0., 0.);
+    let far = Vec2::new(0., 200.);
+    let prediction = predict(&Playfield::default(), launch_point(), Vec2::new(0., 1.), &[far, near]);
+
+    assert_eq!(prediction.hit, Some(near));
+    assert!(prediction.points.len() < aim_dots(&Playfield::default()));
+    assert!(prediction.points.iter().all(|point| point.y < near.y));
+}
+
+#[test]
+fn path_grazing_a_block_hits_it() {
+    let playfield = Playfield::default();
+    // the ball's edge overlaps the block, its center stays clear of it by
+    // more than half a ball
+    let offset = playfield.cell_size / 2. + CONFIG.ball_size * 0.75;
+    let block = launch_point() + Vec2::new(offset, 200.);
+    let prediction = predict(&playfield, launch_point(), Vec2::new(0., 1.), &[block]);
+
+    assert_eq!(prediction.hit, Some(block));
+}
+
+#[test]
+fn launch_angle_is_clamped_above_the_floor() {
+    let min_angle = CONFIG.min_launch_angle.to_radians();
+    let along_floor = launch_point() + Vec2::new(100., 0.);
+    let direction = launch_direction(along_floor, launch_point());
+    assert!((direction.y.atan2(direction.x) - min_angle).abs() < 1e-4);
+    assert!((direction.length() - 1.).abs() < 1e-4);
+
+    let up = Vec2::new(0., Playfield::default().max().y);
+    assert!((launch_direction(up, launch_point()) - Vec2::new(0., 1.)).length() < 1e-4);
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use bevy::prelude::*;
+use brickgame::GameState;
+use brickgame::blocktype::{ADD_BALL, ARMORED, BOMB, BlockDef, BlockRegistry, BlockShape, CIRCLE, Corner, DEFLECTOR, HitEffect, LASER_ROW, SPLITTER, STANDARD, TRIANGLES, WALL, hit_from_above};
+use brickgame::entity::{Ball, Block, Burst, GridPos, SplitBall};
+use brickgame::resource::{HasWon, Playfield, Shooter};
+use common::*;
+use heron::{CollisionShape, Velocity};
+
+#[test]
+fn registry_has_the_generated_types() {
+    let registry = BlockRegistry::default();
+    assert!(registry.is_solid(STANDARD));
+    assert!(!registry.is_solid(ADD_BALL));
+    assert_eq!(registry.get(ADD_BALL).unwrap().on_hit, vec![HitEffect::AddBall, HitEffect::Consume]);
+}
+
+#[test]
+fn new_block_types_are_plain_data() {
+    let fountain: BlockDef = ron::de::from_str(
+        r#"(
+            sensor: true,
+            label: Some("++"),
+            on_hit: [AddBall],
+        )"#,
+    )
+    .unwrap();
+    let mut app = app();
+    app.world
+        .get_resource_mut::<BlockRegistry>()
+        .unwrap()
+        .types
+        .insert("fountain".to_string(), fountain);
+    start_empty_round(&mut app);
+    let center_column = Playfield::default().columns / 2;
+    place(&mut app, (center_column, 5), Block::new("fountain", 1));
+    keep_round_alive(&mut app);
+    let count = app.world.get_resource::<Shooter>().unwrap().count;
+    shoot(&mut app, Playfield::default().center());
+
+    // not consumed, so every ball passing through adds one
+    let frames = step_until(&mut app, 600, |world| {
+        world.get_resource::<Shooter>().unwrap().count > count + 1
+    });
+    assert!(frames.is_some(), "the fountain stopped adding balls");
+    assert_eq!(blocks(&mut app.world).iter().filter(|(_, block)| block.kind == "fountain").count(), 1);
+}
+
+#[test]
+fn bombs_damage_their_neighbours_and_chain() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place(&mut app, (5, 5), Block::new(BOMB, 1));
+    place_standard(&mut app, (4, 6), 8);
+    // in range of the first bomb only, and killed by the explosion
+    place(&mut app, (6, 5), Block::new(BOMB, 2));
+    // only reached by the chained explosion
+    place_standard(&mut app, (7, 4), 1);
+    place_standard(&mut app, (9, 5), 8);
+    place_add_ball(&mut app, (4, 4));
+
+    // detonate the first bomb as a ball would
+    for (mut block, grid_pos) in app.world.query::<(&mut Block, &GridPos)>().iter_mut(&mut app.world) {
+        if *grid_pos == GridPos(5, 5) {
+            block.health = 0;
+        }
+    }
+    step(&mut app, 1);
+    assert!(app.world.query::<&Burst>().iter(&app.world).next().is_some());
+    step(&mut app, 5);
+
+    let mut remaining: Vec<(GridPos, Block)> = app
+        .world
+        .query::<(&GridPos, &Block)>()
+        .iter(&app.world)
+        .map(|(grid_pos, block)| (*grid_pos, block.clone()))
+        .collect();
+    remaining.sort_by_key(|(grid_pos, _)| (grid_pos.0, grid_pos.1));
+    assert_eq!(
+        remaining,
+        vec![
+            (GridPos(4, 4), Block::new(ADD_BALL, 1)),
+            (GridPos(4, 6), Block::new(STANDARD, 3)),
+            (GridPos(9, 5), Block::new(STANDARD, 8)),
+        ]
+    );
+}
+
+fn health_at(world: &mut World, at: GridPos) -> Option<u32> {
+    world
+        .query::<(&GridPos, &Block)>()
+        .iter(world)
+        .find(|(grid_pos, _)| **grid_pos == at)
+        .map(|(_, block)| block.health)
+}
+
+#[test]
+fn lasers_damage_their_row_on_every_pass() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    let center_column = Playfield::default().columns / 2;
+    place(&mut app, (center_column, 3), Block::new(LASER_ROW, 1));
+    place_standard(&mut app, (1, 3), 50);
+    place_standard(&mut app, (12, 3), 1);
+    place_standard(&mut app, (1, 4), 50);
+    // only standard blocks are cut
+    place(&mut app, (3, 3), Block::new(ARMORED, 5));
+    place(&mut app, (10, 3), Block::new(CIRCLE, 5));
+    shoot(&mut app, Playfield::default().center());
+
+    let frames = step_until(&mut app, 600, |world| health_at(world, GridPos(12, 3)).is_none());
+    assert!(frames.is_some(), "the laser never fired");
+    step(&mut app, 60);
+
+    assert!(health_at(&mut app.world, GridPos(1, 3)).unwrap() < 49);
+    assert_eq!(health_at(&mut app.world, GridPos(1, 4)), Some(50));
+    assert_eq!(health_at(&mut app.world, GridPos(3, 3)), Some(5));
+    assert_eq!(health_at(&mut app.world, GridPos(10, 3)), Some(5));
+    assert!(health_at(&mut app.world, GridPos(center_column as i32, 3)).is_some());
+}
+
+#[test]
+fn permanent_blocks_drop_off_and_do_not_need_clearing() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place(&mut app, (3, 0), Block::new(LASER_ROW, 1));
+
+    // lasers at the loss line leave instead of ending the game
+    for _ in 0..4 {
+        move_blocks(&mut app);
+        assert_eq!(state(&app.world), GameState::Aiming);
+    }
+    assert!(blocks(&mut app.world).is_empty());
+
+    place(&mut app, (3, 5), Block::new(LASER_ROW, 1));
+    place_standard(&mut app, (7, 5), 1);
+    shoot(&mut app, Playfield::default().center());
+    let frames = step_until(&mut app, 600, |world| state(world) == GameState::Init);
+    assert!(frames.is_some(), "round never ended");
+    assert_eq!(*app.world.get_resource::<HasWon>().unwrap(), Some(true));
+}
+
+/// Starts a round with a single ball and `kind` right in its path, aimed
+/// `slant` units right of the block's center.
+fn single_ball_through(kind: &str, slant: f32) -> App {
+    let mut app = app();
+    start_empty_round(&mut app);
+    app.world.get_resource_mut::<Shooter>().unwrap().count = 1;
+    let center_column = Playfield::default().columns / 2;
+    place(&mut app, (center_column, 3), Block::new(kind, 1));
+    keep_round_alive(&mut app);
+    let block = Playfield::default().cell_center((center_column as i32, 3));
+    shoot(&mut app, block + Vec2::new(slant, 0.));
+    app
+}
+
+#[test]
+fn splitters_add_a_temporary_mirrored_ball() {
+    // off-axis, so a mirrored ball differs from a copy
+    let mut app = single_ball_through(SPLITTER, Playfield::default().cell_size / 3.);
+    let frames = step_until(&mut app, 300, |world| ball_count(world) == 2);
+    assert!(frames.is_some(), "the ball was never split");
+
+    let mut velocities: Vec<(Vec3, bool)> = app
+        .world
+        .query_filtered::<(&Velocity, Option<&SplitBall>), With<Ball>>()
+        .iter(&app.world)
+        .map(|(velocity, split)| (velocity.linear, split.is_some()))
+        .collect();
+    velocities.sort_by_key(|(_, split)| *split);
+    let (original, split) = (velocities[0].0, velocities[1].0);
+    assert!(velocities[1].1 && !velocities[0].1);
+    assert!(original.x > 1.);
+    assert!((split.x + original.x).abs() < 1e-3);
+    assert!((split.y - original.y).abs() < 1e-3);
+    assert_eq!(app.world.get_resource::<Shooter>().unwrap().count, 1);
+    assert!(blocks(&mut app.world).iter().all(|(_, block)| block.kind != SPLITTER));
+}
+
+#[test]
+fn deflectors_send_the_ball_elsewhere() {
+    let mut app = single_ball_through(DEFLECTOR, 0.);
+    let frames = step_until(&mut app, 300, |world| {
+        blocks(world).iter().all(|(_, block)| block.kind != DEFLECTOR)
+    });
+    assert!(frames.is_some(), "the deflector was never hit");
+    step(&mut app, 1);
+
+    let velocity = app
+        .world
+        .query_filtered::<&Velocity, With<Ball>>()
+        .iter(&app.world)
+        .next()
+        .expect("the ball is gone")
+        .linear;
+    assert!(velocity.x.abs() > 1.);
+}
+
+#[test]
+fn walls_do_not_need_clearing() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place(&mut app, (3, 5), Block::new(WALL, 1));
+    place_standard(&mut app, (Playfield::default().columns / 2, 5), 1);
+    shoot(&mut app, Playfield::default().center());
+
+    let frames = step_until(&mut app, 600, |world| state(world) == GameState::Init);
+    assert!(frames.is_some(), "round never ended");
+    assert_eq!(*app.world.get_resource::<HasWon>().unwrap(), Some(true));
+}
+
+#[test]
+fn armor_only_gives_way_from_above() {
+    let block = Vec2::new(0., 0.);
+    assert!(hit_from_above(Vec2::new(10., 40.), block));
+    assert!(!hit_from_above(Vec2::new(0., -40.), block));
+    assert!(!hit_from_above(Vec2::new(40., 10.), block));
+
+    // every ball hits it from below
+    let mut app = single_ball_through(ARMORED, 0.);
+    let frames = step_until(&mut app, 600, |world| state(world) == GameState::Aiming);
+    assert!(frames.is_some(), "round never ended");
+    let center_column = Playfield::default().columns as i32 / 2;
+    // the row moved down once the round was over
+    assert_eq!(health_at(&mut app.world, GridPos(center_column, 2)), Some(1));
+
+    // power-ups still break it
+    let bomb_row = 2;
+    place(&mut app, (center_column as usize + 1, bomb_row), Block::new(BOMB, 1));
+    for (mut block, grid_pos) in app.world.query::<(&mut Block, &GridPos)>().iter_mut(&mut app.world) {
+        if block.kind == BOMB {
+            assert_eq!(grid_pos.1, bomb_row as i32);
+            block.health = 0;
+        }
+    }
+    step(&mut app, 3);
+    assert_eq!(health_at(&mut app.world, GridPos(center_column, 2)), None);
+}
+
+#[test]
+fn shaped_blocks_collide_with_their_shape() {
+    let registry = BlockRegistry::default();
+    assert_eq!(registry.get(CIRCLE).unwrap().shape, BlockShape::Circle);
+    for (kind, corner) in TRIANGLES.iter().zip(Corner::ALL.iter()) {
+        assert_eq!(registry.get(kind).unwrap().shape, BlockShape::Triangle(*corner));
+        assert!(registry.is_damageable(kind));
+    }
+
+    let mut app = app();
+    start_empty_round(&mut app);
+    place(&mut app, (2, 5), Block::new(CIRCLE, 3));
+    place(&mut app, (4, 5), Block::new(TRIANGLES[0], 3));
+    let mut shapes: Vec<(GridPos, CollisionShape)> = app
+        .world
+        .query::<(&GridPos, &CollisionShape)>()
+        .iter(&app.world)
+        .map(|(grid_pos, shape)| (*grid_pos, shape.clone()))
+        .collect();
+    shapes.sort_by_key(|(grid_pos, _)| grid_pos.0);
+    assert!(matches!(shapes[0].1, CollisionShape::Sphere { .. }));
+    assert!(matches!(&shapes[1].1, CollisionShape::ConvexHull { points } if points.len() == 3));
+}
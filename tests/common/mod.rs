@@ -0,0 +1,148 @@
+#![allow(dead_code)]
+
+use bevy::ecs::system::System;
+use bevy::input::ElementState;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use bevy::transform::hierarchy::despawn_with_children_recursive;
+use brickgame::GameState;
+use brickgame::blocktype::{ADD_BALL, BlockRegistry, STANDARD};
+use brickgame::builder::construct_block;
+use brickgame::entity::{Ball, Block};
+use brickgame::gameassets::GameAssets;
+use brickgame::headless::headless_app;
+use brickgame::resource::{MousePos, Playfield};
+
+pub const SEED: u64 = 42;
+
+/// Headless app that already ran its startup systems and sits in `Init`.
+pub fn app() -> App {
+    let mut app = headless_app(SEED).app;
+    wait_for_menu(&mut app);
+    app
+}
+
+/// Steps through startup and `GameState::Loading` until the menu shows.
+pub fn wait_for_menu(app: &mut App) {
+    let frames = step_until(app, 600, |world| state(world) == GameState::Init);
+    assert!(frames.is_some(), "assets never finished loading");
+}
+
+pub fn step(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+/// Steps until `done` holds or `max_frames` ran out, returns the frames needed.
+pub fn step_until(app: &mut App, max_frames: usize, mut done: impl FnMut(&mut World) -> bool) -> Option<usize> {
+    for frame in 0..max_frames {
+        if done(&mut app.world) {
+            return Some(frame);
+        }
+        app.update();
+    }
+    None
+}
+
+pub fn set_state(app: &mut App, state: GameState) {
+    app.world
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .set(state)
+        .unwrap();
+    app.update();
+}
+
+/// Ends the turn and waits until the blocks have slid down a row.
+pub fn move_blocks(app: &mut App) {
+    set_state(app, GameState::MovingBlocks);
+    let frames = step_until(app, 600, |world| state(world) != GameState::MovingBlocks);
+    assert!(frames.is_some(), "the blocks never stopped moving");
+}
+
+pub fn state(world: &World) -> GameState {
+    world.get_resource::<State<GameState>>().unwrap().current().clone()
+}
+
+/// Runs `system` a single time against the app's world, commands included.
+pub fn run_once(app: &mut App, mut system: impl System<In = (), Out = ()>) {
+    system.initialize(&mut app.world);
+    system.run((), &mut app.world);
+    system.apply_buffers(&mut app.world);
+}
+
+/// Leaves `Init` and swaps the generated board for an empty one.
+pub fn start_empty_round(app: &mut App) {
+    set_state(app, GameState::Aiming);
+    let blocks: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<Block>>()
+        .iter(&app.world)
+        .collect();
+    for block in blocks {
+        despawn_with_children_recursive(&mut app.world, block);
+    }
+}
+
+/// Aims at world coordinates `target` and fires.
+pub fn shoot(app: &mut App, target: Vec2) {
+    *app.world.get_resource_mut::<MousePos>().unwrap() = target;
+    set_state(app, GameState::Shooting);
+}
+
+pub fn blocks(world: &mut World) -> Vec<(Entity, Block)> {
+    world
+        .query::<(Entity, &Block)>()
+        .iter(world)
+        .map(|(entity, block)| (entity, block.clone()))
+        .collect()
+}
+
+pub fn ball_count(world: &mut World) -> usize {
+    world.query_filtered::<Entity, With<Ball>>().iter(world).count()
+}
+
+/// Spawns `block` at `field_pos` as the game would.
+pub fn place(app: &mut App, field_pos: (usize, usize), block: Block) {
+    let field_pos = (field_pos.0 as i32, field_pos.1 as i32);
+    run_once(
+        app,
+        (move |mut commands: Commands,
+               mut assets: ResMut<GameAssets>,
+               mut materials: ResMut<Assets<ColorMaterial>>,
+               registry: Res<BlockRegistry>,
+               playfield: Res<Playfield>| {
+            construct_block(&mut commands, &mut assets, &mut materials, &registry, &playfield, field_pos, block.clone())
+                .expect("unknown block type");
+        })
+        .system(),
+    );
+}
+
+pub fn place_standard(app: &mut App, field_pos: (usize, usize), health: u32) {
+    place(app, field_pos, Block::new(STANDARD, health));
+}
+
+pub fn place_add_ball(app: &mut App, field_pos: (usize, usize)) {
+    place(app, field_pos, Block::new(ADD_BALL, 1));
+}
+
+/// Places a sturdy block out of the balls' path, so clearing the others does
+/// not end the round.
+pub fn keep_round_alive(app: &mut App) {
+    place_standard(app, (0, 8), 100);
+}
+
+/// Presses `key` during the next frame, like a real keyboard would.
+pub fn press_key(app: &mut App, key: KeyCode) {
+    app.world
+        .get_resource_mut::<Events<KeyboardInput>>()
+        .unwrap()
+        .send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state: ElementState::Pressed,
+        });
+    app.update();
+}
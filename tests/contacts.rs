@@ -0,0 +1,69 @@
+mod common;
+
+use bevy::prelude::*;
+use brickgame::blocktype::{ADD_BALL, STANDARD};
+use brickgame::entity::Block;
+use brickgame::system::{Contact, ContactSource};
+use common::*;
+
+/// Empty round fed by `send` instead of the physics engine.
+fn external_contacts() -> App {
+    let mut app = app();
+    *app.world.get_resource_mut::<ContactSource>().unwrap() = ContactSource::External;
+    start_empty_round(&mut app);
+    app
+}
+
+fn send(app: &mut App, contacts: &[(Entity, Entity)]) {
+    let mut events = app.world.get_resource_mut::<Events<Contact>>().unwrap();
+    for (ball, block) in contacts {
+        events.send(Contact { ball: *ball, block: *block });
+    }
+}
+
+fn block_at(world: &mut World, kind: &str, health: u32) -> Entity {
+    blocks(world)
+        .into_iter()
+        .find(|(_, block)| block.kind == kind && block.health == health)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+#[test]
+fn damage_lands_on_contact_start() {
+    let mut app = external_contacts();
+    place_standard(&mut app, (3, 5), 5);
+    let block = block_at(&mut app.world, STANDARD, 5);
+    let ball = app.world.spawn().id();
+
+    send(&mut app, &[(ball, block)]);
+    step(&mut app, 1);
+    assert_eq!(app.world.get::<Block>(block).unwrap().health, 4);
+}
+
+#[test]
+fn one_solid_hit_per_ball_and_frame() {
+    let mut app = external_contacts();
+    place_standard(&mut app, (3, 5), 5);
+    place_standard(&mut app, (4, 5), 6);
+    place_add_ball(&mut app, (5, 5));
+    let first = block_at(&mut app.world, STANDARD, 5);
+    let second = block_at(&mut app.world, STANDARD, 6);
+    let sensor = block_at(&mut app.world, ADD_BALL, 1);
+    let ball = app.world.spawn().id();
+    let other_ball = app.world.spawn().id();
+
+    // grazing two blocks and reporting the first one twice
+    send(&mut app, &[(ball, first), (ball, second), (ball, first), (ball, sensor), (other_ball, second)]);
+    step(&mut app, 1);
+
+    assert_eq!(app.world.get::<Block>(first).unwrap().health, 4);
+    assert_eq!(app.world.get::<Block>(second).unwrap().health, 5);
+    // sensors are passed through, not bounced off
+    assert!(app.world.get::<Block>(sensor).is_none());
+
+    // the next frame counts again
+    send(&mut app, &[(ball, second)]);
+    step(&mut app, 1);
+    assert_eq!(app.world.get::<Block>(second).unwrap().health, 4);
+}
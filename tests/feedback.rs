@@ -0,0 +1,84 @@
+mod common;
+
+use bevy::prelude::*;
+use brickgame::blocktype::{BlockPalette, STANDARD};
+use brickgame::entity::{Block, HitPulse, Shard};
+use common::*;
+
+fn material_of(world: &mut World, health: u32) -> Handle<ColorMaterial> {
+    world
+        .query::<(&Block, &Handle<ColorMaterial>)>()
+        .iter(world)
+        .find(|(block, _)| block.kind == STANDARD && block.health == health)
+        .map(|(_, material)| material.clone())
+        .unwrap()
+}
+
+fn hit(world: &mut World, health: u32) {
+    for mut block in world.query::<&mut Block>().iter_mut(world) {
+        if block.health == health {
+            block.health -= 1;
+        }
+    }
+}
+
+#[test]
+fn blocks_are_shaded_by_relative_health() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_standard(&mut app, (1, 5), 20);
+    place_standard(&mut app, (2, 5), 1);
+    place_standard(&mut app, (3, 5), 1);
+    step(&mut app, 1);
+
+    assert_eq!(app.world.get_resource::<BlockPalette>().unwrap().max_health, 20);
+    assert_eq!(material_of(&mut app.world, 1), material_of(&mut app.world, 1));
+    let weakest = material_of(&mut app.world, 1);
+    assert_ne!(material_of(&mut app.world, 20), weakest);
+    let weak_color = app.world.get_resource::<Assets<ColorMaterial>>().unwrap().get(&weakest).unwrap().color;
+
+    // the strongest block is gone, so the weak ones are the strongest now
+    hit(&mut app.world, 20);
+    for mut block in app.world.query::<&mut Block>().iter_mut(&mut app.world) {
+        if block.health == 19 {
+            block.health = 0;
+        }
+    }
+    step(&mut app, 1);
+    assert_eq!(app.world.get_resource::<BlockPalette>().unwrap().max_health, 1);
+    let strongest = material_of(&mut app.world, 1);
+    let strong_color = app.world.get_resource::<Assets<ColorMaterial>>().unwrap().get(&strongest).unwrap().color;
+    assert_ne!(strong_color, weak_color);
+}
+
+#[test]
+fn hits_pulse_and_destroyed_blocks_shatter() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_standard(&mut app, (4, 5), 2);
+    step(&mut app, 1);
+    assert_eq!(app.world.query::<&HitPulse>().iter(&app.world).count(), 0);
+
+    hit(&mut app.world, 2);
+    step(&mut app, 1);
+    assert_eq!(app.world.query::<&HitPulse>().iter(&app.world).count(), 1);
+    let frames = step_until(&mut app, 60, |world| world.query::<&HitPulse>().iter(world).next().is_none());
+    assert!(frames.is_some(), "the pulse never ended");
+    let scale = app.world.query_filtered::<&Transform, With<Block>>().iter(&app.world).next().unwrap().scale;
+    assert_eq!(scale, Vec3::ONE);
+    // the flash left the block's children along with the world
+    let children: Vec<Entity> = app
+        .world
+        .query_filtered::<&Children, With<Block>>()
+        .iter(&app.world)
+        .flat_map(|children| children.iter().copied())
+        .collect();
+    assert!(children.iter().all(|child| app.world.get_entity(*child).is_some()));
+
+    hit(&mut app.world, 1);
+    step(&mut app, 1);
+    assert!(blocks(&mut app.world).is_empty());
+    assert_eq!(app.world.query::<&Shard>().iter(&app.world).count(), 4);
+    let frames = step_until(&mut app, 60, |world| world.query::<&Shard>().iter(world).next().is_none());
+    assert!(frames.is_some(), "the shards never went away");
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use bevy::prelude::*;
+use brickgame::GameState;
+use brickgame::headless::headless_app;
+use brickgame::highscore::{Board, HighScoreEntry, HighScores, LastBoard, MAX_SEED_TABLES, TOP_N};
+use brickgame::level::SelectedLevel;
+use brickgame::resource::{GameMode, HasWon, LevelSeed};
+use common::*;
+
+fn entry(name: &str, score: usize) -> HighScoreEntry {
+    HighScoreEntry { name: name.to_string(), score, turns: 1 }
+}
+
+#[test]
+fn tables_keep_the_best_entries_per_board() {
+    let mut high_scores = HighScores::default();
+    for score in 1..=TOP_N + 2 {
+        high_scores.insert(GameMode::Classic, Board::Seed(7), entry("emi", score));
+    }
+    high_scores.insert(GameMode::Endless, Board::Seed(7), entry("other", 3));
+
+    let table = high_scores.table(GameMode::Classic, &Board::Seed(7)).unwrap();
+    assert_eq!(table.entries.len(), TOP_N);
+    assert_eq!(table.entries[0].score, TOP_N + 2);
+    assert_eq!(table.entries[TOP_N - 1].score, 3);
+
+    assert!(!high_scores.qualifies(GameMode::Classic, &Board::Seed(7), 3));
+    assert!(high_scores.qualifies(GameMode::Classic, &Board::Seed(7), 4));
+    assert!(high_scores.qualifies(GameMode::Classic, &Board::Seed(8), 1));
+    assert!(!high_scores.qualifies(GameMode::Classic, &Board::Seed(8), 0));
+    assert_eq!(high_scores.table(GameMode::Endless, &Board::Seed(7)).unwrap().entries.len(), 1);
+}
+
+#[test]
+fn only_the_latest_seed_tables_are_kept() {
+    let mut high_scores = HighScores::default();
+    let level = Board::Level("levels/tutorial.level".to_string());
+    high_scores.insert(GameMode::Classic, level.clone(), entry("emi", 5));
+    for seed in 0..MAX_SEED_TABLES as u64 {
+        high_scores.insert(GameMode::Classic, Board::Seed(seed), entry("emi", 5));
+    }
+    // playing an old board again keeps its table
+    high_scores.insert(GameMode::Classic, Board::Seed(0), entry("emi", 6));
+    for seed in MAX_SEED_TABLES as u64..MAX_SEED_TABLES as u64 + 5 {
+        high_scores.insert(GameMode::Classic, Board::Seed(seed), entry("emi", 5));
+    }
+
+    assert_eq!(high_scores.tables.len(), MAX_SEED_TABLES + 1);
+    assert!(high_scores.table(GameMode::Classic, &level).is_some());
+    assert!(high_scores.table(GameMode::Classic, &Board::Seed(0)).is_some());
+    assert!(high_scores.table(GameMode::Classic, &Board::Seed(5)).is_none());
+    assert!(high_scores.table(GameMode::Classic, &Board::Seed(6)).is_some());
+}
+
+#[test]
+fn tables_survive_a_save() {
+    let path = std::env::temp_dir()
+        .join(format!("brickgame-test-{}", std::process::id()))
+        .join("highscores.ron");
+    let mut high_scores = HighScores::load(Some(path.clone()));
+    assert!(high_scores.tables.is_empty());
+    high_scores.insert(GameMode::Endless, Board::Seed(42), entry("emi", 120));
+    high_scores.save().unwrap();
+
+    let loaded = HighScores::load(Some(path.clone()));
+    assert_eq!(loaded.table(GameMode::Endless, &Board::Seed(42)).unwrap().entries, vec![entry("emi", 120)]);
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn screen_shows_the_board_just_played() {
+    let mut app = app();
+    app.world.get_resource_mut::<LevelSeed>().unwrap().fixed = false;
+    let played = app.world.get_resource::<LevelSeed>().unwrap().seed;
+    app.world
+        .get_resource_mut::<HighScores>()
+        .unwrap()
+        .insert(GameMode::Classic, Board::Seed(played), entry("emi", 30));
+    set_state(&mut app, GameState::Aiming);
+    *app.world.get_resource_mut::<HasWon>().unwrap() = Some(false);
+    set_state(&mut app, GameState::Init);
+    assert_ne!(app.world.get_resource::<LevelSeed>().unwrap().seed, played);
+    assert_eq!(
+        *app.world.get_resource::<Option<LastBoard>>().unwrap(),
+        Some(LastBoard { mode: GameMode::Classic, board: Board::Seed(played) })
+    );
+
+    app.world.get_resource_mut::<State<GameState>>().unwrap().push(GameState::HighScores).unwrap();
+    app.update();
+    let lines: Vec<String> = app
+        .world
+        .query::<&Text>()
+        .iter(&app.world)
+        .map(|text| text.sections[0].value.clone())
+        .collect();
+    assert!(lines.iter().any(|line| line.contains(&format!("seed {}", played))));
+    assert!(lines.iter().any(|line| line.contains("emi")));
+}
+
+#[test]
+fn level_runs_share_a_table_whatever_the_seed() {
+    let mut builder = headless_app(SEED);
+    builder.insert_resource(SelectedLevel::new("levels/tutorial.level"));
+    let mut app = builder.app;
+    wait_for_menu(&mut app);
+    app.world.get_resource_mut::<LevelSeed>().unwrap().fixed = false;
+    for _ in 0..2 {
+        set_state(&mut app, GameState::Aiming);
+        *app.world.get_resource_mut::<HasWon>().unwrap() = Some(false);
+        set_state(&mut app, GameState::Init);
+        assert_eq!(
+            *app.world.get_resource::<Option<LastBoard>>().unwrap(),
+            Some(LastBoard { mode: GameMode::Classic, board: Board::Level("levels/tutorial.level".to_string()) })
+        );
+    }
+}
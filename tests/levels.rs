@@ -0,0 +1,107 @@
+mod common;
+
+use bevy::prelude::*;
+use brickgame::GameState;
+use brickgame::blocktype::ADD_BALL;
+use brickgame::entity::Block;
+use brickgame::headless::headless_app;
+use brickgame::level::{Level, SelectedLevel};
+use brickgame::levelgen::voronoi_map_gen;
+use brickgame::resource::{GameRng, Playfield, Shooter};
+use common::*;
+
+fn generated_board(seed: u64) -> Vec<(i32, i32, Block)> {
+    let mut app = headless_app(seed).app;
+    wait_for_menu(&mut app);
+    set_state(&mut app, GameState::Aiming);
+    let mut board: Vec<(i32, i32, Block)> = app
+        .world
+        .query::<(&Transform, &Block)>()
+        .iter(&app.world)
+        .map(|(transform, block)| {
+            (transform.translation.x as i32, transform.translation.y as i32, block.clone())
+        })
+        .collect();
+    board.sort_by_key(|(x, y, _)| (*x, *y));
+    board
+}
+
+#[test]
+fn same_seed_same_board() {
+    let board = generated_board(SEED);
+    assert!(!board.is_empty());
+    assert_eq!(board, generated_board(SEED));
+}
+
+#[test]
+fn generated_boards_fit_inside_the_walls() {
+    let top = Playfield::default().max().y;
+    for (_, y, _) in generated_board(SEED) {
+        assert!((y as f32) < top);
+    }
+}
+
+#[test]
+fn generated_maps_keep_the_voronoi_layout_rules() {
+    let (width, height) = (15, 10);
+    let mut filled = 0;
+    let mut add_balls = 0;
+    for seed in 0..20 {
+        let bricks = voronoi_map_gen((width, height), &mut GameRng::from_seed(seed).0);
+        let mut positions: Vec<_> = bricks.iter().map(|brick| brick.position).collect();
+        positions.sort_unstable();
+        positions.dedup();
+        assert_eq!(positions.len(), bricks.len());
+        assert!(positions.iter().all(|(x, y)| *x < width && *y < height));
+        filled += bricks.len();
+        add_balls += bricks.iter().filter(|brick| brick.kind == ADD_BALL).count();
+    }
+    // empty regions leave gaps, but about half of the field is filled
+    let density = filled as f32 / (20 * width * height) as f32;
+    assert!(density > 0.35 && density < 0.75, "density {}", density);
+    // pickups only take their site cell, a filled region would be ~7 per map
+    assert!(add_balls > 0 && add_balls < 60, "{} pickups", add_balls);
+}
+
+#[test]
+fn different_seed_different_board() {
+    assert_ne!(generated_board(1), generated_board(2));
+}
+
+#[test]
+fn hand_authored_level_replaces_generated_map() {
+    let mut builder = headless_app(SEED);
+    builder.insert_resource(SelectedLevel::new("levels/tutorial.level"));
+    let mut app = builder.app;
+    // the menu waits for the level
+    wait_for_menu(&mut app);
+    let handle = app.world.get_resource::<SelectedLevel>().unwrap().handle.clone().unwrap();
+    assert!(app.world.get_resource::<Assets<Level>>().unwrap().get(handle).is_some());
+
+    set_state(&mut app, GameState::Aiming);
+
+    let blocks = blocks(&mut app.world);
+    assert_eq!(blocks.len(), 10);
+    assert_eq!(blocks.iter().filter(|(_, block)| block.kind == ADD_BALL).count(), 1);
+    assert_eq!(app.world.get_resource::<Shooter>().unwrap().count, 5);
+}
+
+#[test]
+fn bricks_outside_the_walls_are_dropped() {
+    let level: Level = ron::de::from_str(
+        "(width: 20, height: 20, balls: 3, bricks: [
+            (position: (3, 4), kind: \"standard\"),
+            (position: (17, 4), kind: \"standard\"),
+            (position: (3, 14), kind: \"standard\"),
+        ])",
+    )
+    .unwrap();
+    let bricks = level.bricks(&Playfield::centered(15, 15, 60.));
+    assert_eq!(bricks.iter().map(|brick| brick.position).collect::<Vec<_>>(), vec![(3, 4)]);
+}
+
+#[test]
+fn levels_add_one_row_per_turn_by_default() {
+    let level: Level = ron::de::from_str("(width: 15, height: 10, balls: 3, bricks: [])").unwrap();
+    assert_eq!(level.rows_per_turn, 1);
+}
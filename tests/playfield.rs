@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use brickgame::resource::Playfield;
+
+#[test]
+fn cell_positions_round_trip() {
+    let playfield = Playfield::centered(15, 15, 60.);
+    for field_pos in [(0, 0), (14, 9), (3, -2)].iter() {
+        let center = playfield.cell_center(*field_pos);
+        assert_eq!(playfield.cell_at(center), *field_pos);
+        assert_eq!(playfield.cell_at(center + Vec2::new(29., -29.)), *field_pos);
+    }
+}
+
+#[test]
+fn top_row_stays_inside_the_walls() {
+    let playfield = Playfield::centered(15, 15, 60.);
+    let top = playfield.cell_center((0, playfield.top_row() as i32));
+    assert!(top.y < playfield.max().y);
+    assert!(playfield.cell_center((0, playfield.top_row() as i32 + 1)).y > playfield.max().y);
+    // too short for any row above the offset
+    assert_eq!(Playfield::centered(10, 3, 40.).top_row(), 0);
+}
+
+#[test]
+fn moved_playfield_keeps_its_grid() {
+    let centered = Playfield::centered(10, 12, 40.);
+    let moved = Playfield { origin: Vec2::new(100., 50.), ..centered };
+    let offset = moved.origin - centered.origin;
+
+    assert_eq!(moved.cell_center((2, 3)), centered.cell_center((2, 3)) + offset);
+    assert_eq!(moved.center(), centered.center() + offset);
+}
+
+#[test]
+fn wide_windows_are_pillarboxed() {
+    let playfield = Playfield::centered(15, 15, 60.);
+    let window = Vec2::new(1800., 450.);
+
+    // the height limits the scale, the board is 900 units high
+    assert_eq!(playfield.view_scale(window), 2.);
+    assert_eq!(playfield.window_to_world(window / 2., window), playfield.center());
+    let top_middle = playfield.window_to_world(Vec2::new(900., 450.), window);
+    assert_eq!(top_middle, Vec2::new(0., playfield.max().y));
+    // beyond the board on the sides
+    let left_edge = playfield.window_to_world(Vec2::new(0., 225.), window);
+    assert!(left_edge.x < playfield.min().x);
+}
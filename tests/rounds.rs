@@ -0,0 +1,262 @@
+mod common;
+
+use bevy::prelude::*;
+use brickgame::GameState;
+use brickgame::blocktype::STANDARD;
+use brickgame::constants::CONFIG;
+use brickgame::entity::{Ball, Block, Scoreboard};
+use brickgame::headless::headless_app;
+use brickgame::resource::{GameMode, HasWon, LaunchPoint, Playfield, Shooter};
+use brickgame::system::GameEvents;
+use common::*;
+use heron::Velocity;
+
+/// Field column whose center is the launch point.
+const CENTER_COLUMN: usize = 7;
+
+fn straight_up() -> Vec2 {
+    Playfield::default().center()
+}
+
+#[test]
+fn clearing_all_blocks_wins() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_standard(&mut app, (CENTER_COLUMN, 5), 1);
+    shoot(&mut app, straight_up());
+
+    let frames = step_until(&mut app, 600, |world| state(world) == GameState::Init);
+    assert!(frames.is_some(), "round never ended");
+    step(&mut app, 1);
+
+    assert_eq!(*app.world.get_resource::<HasWon>().unwrap(), Some(true));
+    assert!(blocks(&mut app.world).is_empty());
+    assert_eq!(
+        app.world.get_resource::<Scoreboard>().unwrap().score,
+        Scoreboard::HIT_POINTS + Scoreboard::DESTROY_POINTS
+    );
+    assert_eq!(ball_count(&mut app.world), 0);
+}
+
+#[test]
+fn hits_reduce_block_health() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_standard(&mut app, (CENTER_COLUMN, 5), 50);
+    shoot(&mut app, straight_up());
+
+    let frames = step_until(&mut app, 600, |world| {
+        blocks(world).iter().any(|(_, block)| *block != Block::new(STANDARD, 50))
+    });
+    assert!(frames.is_some(), "block was never hit");
+
+    assert_eq!(state(&app.world), GameState::Shooting);
+    assert_eq!(blocks(&mut app.world).len(), 1);
+    assert!(ball_count(&mut app.world) > 0);
+    assert!(app.world.get_resource::<Scoreboard>().unwrap().score >= Scoreboard::HIT_POINTS);
+    assert_eq!(*app.world.get_resource::<HasWon>().unwrap(), None);
+}
+
+#[test]
+fn blocks_reaching_the_bottom_lose() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_standard(&mut app, (CENTER_COLUMN, 0), 10);
+
+    // the lowest row needs three moves to cross the loss line
+    for _ in 0..3 {
+        move_blocks(&mut app);
+        assert_eq!(state(&app.world), GameState::Aiming);
+    }
+    move_blocks(&mut app);
+
+    assert_eq!(state(&app.world), GameState::Init);
+    assert_eq!(*app.world.get_resource::<HasWon>().unwrap(), Some(false));
+    assert!(blocks(&mut app.world).is_empty());
+}
+
+#[test]
+fn blocks_slide_down_before_the_next_turn() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_standard(&mut app, (CENTER_COLUMN, 5), 10);
+    let playfield = Playfield::default();
+    let block_y = |world: &mut World| {
+        world.query_filtered::<&Transform, With<Block>>().iter(world).next().unwrap().translation.y
+    };
+    let start = block_y(&mut app.world);
+
+    set_state(&mut app, GameState::MovingBlocks);
+    step(&mut app, 2);
+    assert_eq!(state(&app.world), GameState::MovingBlocks);
+    let halfway = block_y(&mut app.world);
+    assert!(halfway < start && halfway > start - playfield.cell_size);
+
+    let frames = step_until(&mut app, 600, |world| state(world) == GameState::Aiming);
+    assert!(frames.is_some(), "the blocks never stopped moving");
+    assert_eq!(block_y(&mut app.world), playfield.cell_center((CENTER_COLUMN as i32, 4)).y);
+}
+
+#[test]
+fn add_ball_pickup_adds_a_ball() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    place_add_ball(&mut app, (CENTER_COLUMN, 5));
+    keep_round_alive(&mut app);
+    let count = app.world.get_resource::<Shooter>().unwrap().count;
+    shoot(&mut app, straight_up());
+
+    let frames = step_until(&mut app, 600, |world| {
+        world.get_resource::<Shooter>().unwrap().count > count
+    });
+    assert!(frames.is_some(), "add ball block was never picked up");
+    step(&mut app, 1);
+
+    assert_eq!(app.world.get_resource::<Shooter>().unwrap().count, count + 1);
+    let remaining = blocks(&mut app.world);
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].1, Block::new(STANDARD, 100));
+    assert_eq!(state(&app.world), GameState::Shooting);
+}
+
+#[test]
+fn endless_mode_adds_a_row_every_turn() {
+    let mut builder = headless_app(SEED);
+    builder.insert_resource(GameMode::Endless);
+    let mut app = builder.app;
+    wait_for_menu(&mut app);
+    set_state(&mut app, GameState::Aiming);
+    let first_row = blocks(&mut app.world).len();
+    assert!(first_row > 0);
+
+    move_blocks(&mut app);
+
+    assert_eq!(state(&app.world), GameState::Aiming);
+    assert_eq!(app.world.get_resource::<Scoreboard>().unwrap().turns, 1);
+    let rows: std::collections::HashSet<i32> = app
+        .world
+        .query_filtered::<&Transform, With<Block>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.y as i32)
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert!(blocks(&mut app.world).len() > first_row);
+}
+
+#[test]
+fn next_turn_starts_where_the_first_ball_landed() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    keep_round_alive(&mut app);
+    shoot(&mut app, straight_up() + Vec2::new(300., 0.));
+    assert_eq!(app.world.get_resource::<LaunchPoint>().unwrap().x, 0.);
+
+    let frames = step_until(&mut app, 5000, |world| state(world) == GameState::Aiming);
+    assert!(frames.is_some(), "round never ended");
+
+    let launch_point = app.world.get_resource::<LaunchPoint>().unwrap();
+    assert_ne!(launch_point.x, 0.);
+    let playfield = Playfield::default();
+    assert!(launch_point.x > playfield.min().x && launch_point.x < playfield.max().x);
+    assert_eq!(launch_point.next, None);
+}
+
+#[test]
+fn recall_gathers_the_balls_and_ends_the_round() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    keep_round_alive(&mut app);
+    shoot(&mut app, straight_up());
+    step(&mut app, 20);
+    assert!(ball_count(&mut app.world) > 0);
+
+    press_key(&mut app, KeyCode::R);
+    let frames = step_until(&mut app, 120, |world| state(world) == GameState::Aiming);
+    assert!(frames.is_some(), "recalled balls never came back");
+
+    assert_eq!(ball_count(&mut app.world), 0);
+    assert_eq!(app.world.get_resource::<Scoreboard>().unwrap().turns, 1);
+    assert!(app.world.get_resource::<LaunchPoint>().unwrap().x.abs() < 1.);
+}
+
+#[test]
+fn round_ends_once_every_ball_is_launched_and_gone() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    keep_round_alive(&mut app);
+    app.world.get_resource_mut::<Shooter>().unwrap().count = 3;
+    shoot(&mut app, straight_up());
+    let frames = step_until(&mut app, 120, |world| ball_count(world) > 0);
+    assert!(frames.is_some(), "no ball was launched");
+
+    // like the floor does
+    let retire_all = |world: &mut World| {
+        let balls: Vec<Entity> = world.query_filtered::<Entity, With<Ball>>().iter(world).collect();
+        for ball in balls {
+            world.despawn(ball);
+            world.get_resource_mut::<Events<GameEvents>>().unwrap().send(GameEvents::DestroyBall);
+        }
+    };
+    // balls still to come keep the round going
+    retire_all(&mut app.world);
+    step(&mut app, 1);
+    assert_eq!(state(&app.world), GameState::Shooting);
+
+    let frames = step_until(&mut app, 120, |world| world.get_resource::<Shooter>().unwrap().finished);
+    assert!(frames.is_some(), "not every ball was launched");
+    step(&mut app, 1);
+    // several balls gone in the same frame
+    assert!(ball_count(&mut app.world) > 1);
+    retire_all(&mut app.world);
+    let frames = step_until(&mut app, 60, |world| state(world) == GameState::Aiming);
+    assert!(frames.is_some(), "round never ended");
+}
+
+#[test]
+fn flat_bouncing_balls_get_nudged_down() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    keep_round_alive(&mut app);
+    shoot(&mut app, straight_up());
+    // lay every ball flat until all are fired, they would bounce between the
+    // side walls forever
+    for _ in 0..150 {
+        for mut velocity in app
+            .world
+            .query_filtered::<&mut Velocity, With<Ball>>()
+            .iter_mut(&mut app.world)
+        {
+            velocity.linear = Vec3::new(CONFIG.ballspeed, 0., 0.);
+        }
+        app.update();
+    }
+
+    let frames = step_until(&mut app, 1200, |world| state(world) == GameState::Aiming);
+    assert!(frames.is_some(), "flat balls kept the round going");
+    assert_eq!(ball_count(&mut app.world), 0);
+}
+
+#[test]
+fn balls_bounce_off_the_walls_and_stay_inside() {
+    let mut app = app();
+    start_empty_round(&mut app);
+    keep_round_alive(&mut app);
+    let playfield = Playfield::default();
+    shoot(&mut app, Vec2::new(playfield.max().x, playfield.center().y + 100.));
+
+    let mut bounced = false;
+    for _ in 0..300 {
+        app.update();
+        for (transform, velocity) in app
+            .world
+            .query_filtered::<(&Transform, &Velocity), With<Ball>>()
+            .iter(&app.world)
+        {
+            assert!(transform.translation.x >= playfield.min().x);
+            assert!(transform.translation.x <= playfield.max().x);
+            assert!(transform.translation.y <= playfield.max().y);
+            bounced |= velocity.linear.x < 0.;
+        }
+    }
+    assert!(bounced, "no ball came back from the right wall");
+}
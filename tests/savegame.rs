@@ -0,0 +1,112 @@
+mod common;
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use brickgame::GameState;
+use brickgame::entity::Block;
+use brickgame::headless::headless_app;
+use brickgame::level::SelectedLevel;
+use brickgame::resource::{GameMode, HasWon, LevelSeed, Playfield, Shooter};
+use brickgame::savegame::SaveSlot;
+use common::*;
+
+fn save_path(test: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("brickgame-{}-{}", test, std::process::id()))
+        .join("save.ron")
+}
+
+fn app_with_slot(seed: u64, path: &PathBuf) -> App {
+    let mut builder = headless_app(seed);
+    builder.insert_resource(SaveSlot { path: Some(path.clone()) });
+    let mut app = builder.app;
+    wait_for_menu(&mut app);
+    app
+}
+
+fn board(app: &mut App) -> Vec<((i32, i32), Block)> {
+    let playfield = *app.world.get_resource::<Playfield>().unwrap();
+    let mut board: Vec<((i32, i32), Block)> = app
+        .world
+        .query::<(&Transform, &Block)>()
+        .iter(&app.world)
+        .map(|(transform, block)| (playfield.cell_at(transform.translation.truncate()), block.clone()))
+        .collect();
+    board.sort_by_key(|(position, _)| *position);
+    board
+}
+
+#[test]
+fn aiming_autosaves_and_continue_restores() {
+    let path = save_path("continue");
+    let mut app = app_with_slot(SEED, &path);
+    set_state(&mut app, GameState::Aiming);
+    move_blocks(&mut app);
+    assert_eq!(state(&app.world), GameState::Aiming);
+    let played = board(&mut app);
+
+    let save = SaveSlot { path: Some(path.clone()) }.load().expect("nothing was saved");
+    assert_eq!(save.state, GameState::Aiming);
+    assert_eq!(save.turns, 1);
+    assert_eq!(save.seed, SEED);
+    assert_eq!(save.blocks.len(), played.len());
+
+    let mut resumed = app_with_slot(SEED + 1, &path);
+    press_key(&mut resumed, KeyCode::C);
+    step(&mut resumed, 1);
+
+    assert_eq!(state(&resumed.world), GameState::Aiming);
+    assert_eq!(board(&mut resumed), played);
+    assert_eq!(resumed.world.get_resource::<LevelSeed>().unwrap().seed, SEED);
+    assert_eq!(resumed.world.get_resource::<Shooter>().unwrap().count, save.balls);
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn finished_games_can_not_be_continued() {
+    let path = save_path("finished");
+    let mut app = app_with_slot(SEED, &path);
+    start_empty_round(&mut app);
+    place_standard(&mut app, (7, 0), 10);
+    step(&mut app, 1);
+    for _ in 0..4 {
+        move_blocks(&mut app);
+    }
+
+    assert_eq!(state(&app.world), GameState::Init);
+    assert!(!SaveSlot { path: Some(path.clone()) }.exists());
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn continued_level_games_leave_the_session_settings_alone() {
+    let path = save_path("level");
+    let mut builder = headless_app(SEED);
+    builder
+        .insert_resource(SaveSlot { path: Some(path.clone()) })
+        .insert_resource(SelectedLevel::new("levels/tutorial.level"))
+        .insert_resource(GameMode::Endless);
+    let mut app = builder.app;
+    wait_for_menu(&mut app);
+    set_state(&mut app, GameState::Aiming);
+    let save = SaveSlot { path: Some(path.clone()) }.load().expect("nothing was saved");
+    assert_eq!(save.level.as_deref(), Some("levels/tutorial.level"));
+    assert_eq!(save.mode, GameMode::Endless);
+
+    let mut resumed = app_with_slot(SEED + 1, &path);
+    press_key(&mut resumed, KeyCode::C);
+    step(&mut resumed, 1);
+    assert_eq!(*resumed.world.get_resource::<GameMode>().unwrap(), GameMode::Endless);
+    assert_eq!(
+        resumed.world.get_resource::<SelectedLevel>().unwrap().path.as_deref(),
+        Some("levels/tutorial.level")
+    );
+
+    *resumed.world.get_resource_mut::<HasWon>().unwrap() = Some(false);
+    set_state(&mut resumed, GameState::Init);
+    assert_eq!(*resumed.world.get_resource::<GameMode>().unwrap(), GameMode::Classic);
+    assert_eq!(resumed.world.get_resource::<SelectedLevel>().unwrap().path, None);
+    assert_eq!(resumed.world.get_resource::<LevelSeed>().unwrap().seed, SEED + 1);
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}